@@ -1,15 +1,126 @@
+use std::str::FromStr;
 use image::{DynamicImage, ImageBuffer, Rgb};
-use rayon::{iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator}, slice::{ParallelSlice, ParallelSliceMut}};
-use crate::{ProgressSender, send_progress};
+use rayon::{iter::{IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator}, slice::{ParallelSlice, ParallelSliceMut}};
+use crate::{color::{channel_mixer, linear_to_srgb, parse_hex_color, srgb_to_linear, ChannelMatrix}, enhancement::{bilateral, gaussian_blur, gradients, luma, morphology, sample_bilinear, GradientKernel, MorphOperation, StructuringElement}, BorderMode, ProgressSender, send_progress};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PixelateMode {
+    Mean,
+    Center,
+    Dominant,
+}
+
+impl FromStr for PixelateMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "mean" => Ok(PixelateMode::Mean),
+            "center" => Ok(PixelateMode::Center),
+            "dominant" => Ok(PixelateMode::Dominant),
+            other => Err(format!("Unknown pixelate mode: {}", other)),
+        }
+    }
+}
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmbossOutput {
+    Relief,
+    Color,
+}
+
+impl FromStr for EmbossOutput {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "relief" | "gray" => Ok(EmbossOutput::Relief),
+            "color" => Ok(EmbossOutput::Color),
+            other => Err(format!("Unknown emboss output: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DotShape {
+    Round,
+    Line,
+    Square,
+}
+
+impl FromStr for DotShape {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "round" => Ok(DotShape::Round),
+            "line" => Ok(DotShape::Line),
+            "square" => Ok(DotShape::Square),
+            other => Err(format!("Unknown dot shape: {}", other)),
+        }
+    }
+}
+
+// Screen angles are in degrees; CMYK angles are in cyan, magenta, yellow, black order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HalftoneInks {
+    Cmyk([f32; 4]),
+    Single { angle: f32, color: Rgb<u8> },
+}
+
+impl HalftoneInks {
+    // Ink amount (0-1) of the given ink for an RGB color in 0-1.
+    fn amount(&self, ink: usize, color: [f32; 3]) -> f32 {
+        match self {
+            HalftoneInks::Cmyk(_) => {
+                let black = 1.0 - color[0].max(color[1]).max(color[2]);
+                if ink == 3 {
+                    black
+                } else if black >= 1.0 {
+                    0.0
+                } else {
+                    (1.0 - color[ink] - black) / (1.0 - black)
+                }
+            }
+            HalftoneInks::Single { .. } => 1.0 - luma(&color.map(|channel| (channel * 255.0).round() as u8)) / 255.0,
+        }
+    }
+}
 
 pub fn sepia(img: &DynamicImage, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    channel_mixer(img, &ChannelMatrix::SEPIA, progress_tx)
+}
+
+
+
+pub fn gradient_map(img: &DynamicImage, stops: &[(f32, Rgb<u8>)], progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
     let rgb_img = img.to_rgb8();
     let (width, height) = rgb_img.dimensions();
     let mut out_buffer = ImageBuffer::new(width, height);
     
     send_progress(&progress_tx, 0.0);
 
+    let mut sorted = stops.to_vec();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut palette = [[0u8; 3]; 256];
+    if !sorted.is_empty() {
+        for (value, entry) in palette.iter_mut().enumerate() {
+            let position = value as f32 / 255.0;
+            let upper = sorted.iter().position(|stop| stop.0 >= position).unwrap_or(sorted.len() - 1);
+            let lower = upper.saturating_sub(1);
+            let (start, start_color) = sorted[lower];
+            let (end, end_color) = sorted[upper];
+
+            let t = if end > start { ((position - start) / (end - start)).clamp(0.0, 1.0) } else { 1.0 };
+            for channel in 0..3 {
+                let from = start_color[channel] as f32;
+                let to = end_color[channel] as f32;
+                entry[channel] = (from + (to - from) * t).round() as u8;
+            }
+        }
+    }
+
     let in_pixels = rgb_img.as_raw();
     let out_pixels = out_buffer.as_mut();
 
@@ -17,17 +128,9 @@ pub fn sepia(img: &DynamicImage, progress_tx: Option<ProgressSender>) -> ImageBu
         .par_chunks_exact(3)
         .zip(out_pixels.par_chunks_exact_mut(3))
         .for_each(|(in_pixel, out_pixel)| {
-            let red = in_pixel[0] as f32;
-            let green = in_pixel[1] as f32;
-            let blue = in_pixel[2] as f32;
-
-            let new_red = (red * 0.393) + (green * 0.769) + (blue * 0.189);
-            let new_green = (red * 0.349) + (green * 0.686) + (blue * 0.168);
-            let new_blue = (red * 0.272) + (green * 0.534) + (blue * 0.131);
+            let gray = luma(in_pixel).round() as usize;
 
-            out_pixel[0] = new_red.min(255.0) as u8;
-            out_pixel[1] = new_green.min(255.0) as u8;
-            out_pixel[2] = new_blue.min(255.0) as u8;
+            out_pixel.copy_from_slice(&palette[gray.min(255)]);
         });
 
     send_progress(&progress_tx, 1.0);
@@ -36,7 +139,45 @@ pub fn sepia(img: &DynamicImage, progress_tx: Option<ProgressSender>) -> ImageBu
 
 
 
-pub fn vignette(img: &DynamicImage, strength: f32, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+pub fn duotone(img: &DynamicImage, shadow: Rgb<u8>, highlight: Rgb<u8>, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    gradient_map(img, &[(0.0, shadow), (1.0, highlight)], progress_tx)
+}
+
+
+
+pub fn tritone(img: &DynamicImage, shadow: Rgb<u8>, midtone: Rgb<u8>, highlight: Rgb<u8>, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    gradient_map(img, &[(0.0, shadow), (0.5, midtone), (1.0, highlight)], progress_tx)
+}
+
+
+
+// Parses stops written as "#rrggbb@position" separated by commas, e.g. "#000000@0,#ff8800@0.6,#ffffff@1".
+// Stops without a position are spread evenly.
+pub fn parse_gradient_stops(spec: &str) -> crate::Result<Vec<(f32, Rgb<u8>)>> {
+    let parts: Vec<&str> = spec.split(',').map(str::trim).filter(|part| !part.is_empty()).collect();
+    if parts.len() < 2 {
+        return Err("A gradient needs at least two stops".into());
+    }
+
+    parts
+        .iter()
+        .enumerate()
+        .map(|(index, part)| {
+            let (color, position) = match part.split_once('@') {
+                Some((color, position)) => (color, position.trim().parse::<f32>()?),
+                None => (*part, index as f32 / (parts.len() - 1) as f32),
+            };
+            if !(0.0..=1.0).contains(&position) {
+                return Err(format!("Gradient stop '{}' must be positioned within 0 to 1", part).into());
+            }
+            Ok((position, parse_hex_color(color)?))
+        })
+        .collect()
+}
+
+
+
+pub fn vignette(img: &DynamicImage, strength: f32, linear: bool, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
     let rgb_img = img.to_rgb8();
     let (width, height) = rgb_img.dimensions();
     let mut out_buffer = ImageBuffer::new(width, height);
@@ -64,6 +205,13 @@ pub fn vignette(img: &DynamicImage, strength: f32, progress_tx: Option<ProgressS
                 let distance = ((x_f - center_x).powi(2) + (y_f - center_y).powi(2)).sqrt();
                 let vignette_factor = 1.0 - (distance / max_distance * strength).clamp(0.0, 1.0);
 
+                if linear {
+                    out_row[out_idx] = linear_to_srgb(srgb_to_linear(in_pixels[in_idx]) * vignette_factor);
+                    out_row[out_idx + 1] = linear_to_srgb(srgb_to_linear(in_pixels[in_idx + 1]) * vignette_factor);
+                    out_row[out_idx + 2] = linear_to_srgb(srgb_to_linear(in_pixels[in_idx + 2]) * vignette_factor);
+                    continue;
+                }
+
                 out_row[out_idx] = (in_pixels[in_idx] as f32 * vignette_factor) as u8;
                 out_row[out_idx + 1] = (in_pixels[in_idx + 1] as f32 * vignette_factor) as u8;
                 out_row[out_idx + 2] = (in_pixels[in_idx + 2] as f32 * vignette_factor) as u8;
@@ -115,6 +263,101 @@ pub fn noise(img: &DynamicImage, strength: u8, progress_tx: Option<ProgressSende
 
 
 
+// Pixelates the (x, y, width, height) region; a zero width or height extends it to the image edge.
+// Blocks are aligned to the region's corner so a region gets whole blocks.
+pub fn pixelate(img: &DynamicImage, block_size: u32, mode: PixelateMode, region: (u32, u32, u32, u32), progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (img_width, img_height) = rgb_img.dimensions();
+    let mut out_buffer = rgb_img.clone();
+
+    send_progress(&progress_tx, 0.0);
+
+    let (x, y, width, height) = region;
+    let region_x = x.min(img_width.saturating_sub(1));
+    let region_y = y.min(img_height.saturating_sub(1));
+    let region_width = if width == 0 { img_width - region_x } else { width.min(img_width - region_x) };
+    let region_height = if height == 0 { img_height - region_y } else { height.min(img_height - region_y) };
+    let block_size = block_size.max(1);
+
+    let stride = (img_width * 3) as usize;
+    let in_pixels = rgb_img.as_raw();
+    let region_rows = &mut out_buffer.as_mut()[region_y as usize * stride..(region_y + region_height) as usize * stride];
+
+    region_rows
+        .par_chunks_mut(stride * block_size as usize)
+        .enumerate()
+        .for_each(|(band, band_rows)| {
+            let top = region_y + band as u32 * block_size;
+            let rows = (band_rows.len() / stride) as u32;
+
+            let mut left = region_x;
+            while left < region_x + region_width {
+                let columns = block_size.min(region_x + region_width - left);
+                let color = block_color(in_pixels, img_width, left, top, columns, rows, mode);
+
+                for row in 0..rows {
+                    for column in left..left + columns {
+                        let idx = row as usize * stride + (column * 3) as usize;
+                        band_rows[idx..idx + 3].copy_from_slice(&color);
+                    }
+                }
+                left += columns;
+            }
+        });
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+fn block_color(pixels: &[u8], img_width: u32, left: u32, top: u32, columns: u32, rows: u32, mode: PixelateMode) -> [u8; 3] {
+    let pixel_at = |x: u32, y: u32| {
+        let idx = ((y * img_width + x) * 3) as usize;
+        [pixels[idx], pixels[idx + 1], pixels[idx + 2]]
+    };
+
+    match mode {
+        PixelateMode::Center => pixel_at(left + columns / 2, top + rows / 2),
+        PixelateMode::Mean => {
+            let mut sum = [0u32; 3];
+            for y in top..top + rows {
+                for x in left..left + columns {
+                    let pixel = pixel_at(x, y);
+                    sum[0] += pixel[0] as u32;
+                    sum[1] += pixel[1] as u32;
+                    sum[2] += pixel[2] as u32;
+                }
+            }
+            let count = columns * rows;
+            sum.map(|channel| ((channel + count / 2) / count) as u8)
+        }
+        PixelateMode::Dominant => {
+            // Most common color at 4 bits per channel, averaged within that bucket
+            let mut buckets: std::collections::HashMap<u16, (u32, [u32; 3])> = std::collections::HashMap::new();
+            for y in top..top + rows {
+                for x in left..left + columns {
+                    let pixel = pixel_at(x, y);
+                    let key = ((pixel[0] as u16 >> 4) << 8) | ((pixel[1] as u16 >> 4) << 4) | (pixel[2] as u16 >> 4);
+                    let entry = buckets.entry(key).or_insert((0, [0; 3]));
+                    entry.0 += 1;
+                    entry.1[0] += pixel[0] as u32;
+                    entry.1[1] += pixel[1] as u32;
+                    entry.1[2] += pixel[2] as u32;
+                }
+            }
+
+            let (count, sum) = buckets
+                .into_values()
+                .max_by_key(|(count, _)| *count)
+                .unwrap_or((1, [0; 3]));
+            sum.map(|channel| (channel / count) as u8)
+        }
+    }
+}
+
+
+
 pub fn oil_painting(img: &DynamicImage, radius: u32, intensity: u32, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
     let rgb_img = img.to_rgb8();
     let (width, height) = rgb_img.dimensions();
@@ -337,4 +580,276 @@ unsafe fn set_oil_pixel_unsafe(result_ptr: *mut u8, out_idx: usize, max_idx: usi
             *result_ptr.add(out_idx + 2) = 0;
         }
     }
-}
\ No newline at end of file
+}
+
+
+
+// Treats luminance as a height map `depth` pixels tall and lights it from `angle` degrees
+// (0 = from the right, counter-clockwise) at `elevation` degrees above the surface. Flat areas come
+// out mid gray; the color output overlays that relief onto the original pixels.
+pub fn emboss(img: &DynamicImage, angle: f32, elevation: f32, depth: f32, output: EmbossOutput, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+
+    send_progress(&progress_tx, 0.0);
+
+    let heights: Vec<f32> = rgb_img.as_raw().par_chunks_exact(3).map(|pixel| luma(pixel) / 255.0 * depth).collect();
+    // Sobel sums eight times the per-pixel slope
+    let (gx, gy) = gradients(&heights, width, height, GradientKernel::SOBEL);
+
+    send_progress(&progress_tx, 0.5);
+
+    let (azimuth, elevation) = (angle.to_radians(), elevation.to_radians());
+    let light = [azimuth.cos() * elevation.cos(), -azimuth.sin() * elevation.cos(), elevation.sin()];
+
+    let mut out_buffer = ImageBuffer::new(width, height);
+    out_buffer
+        .as_mut()
+        .par_chunks_exact_mut(3)
+        .zip(rgb_img.as_raw().par_chunks_exact(3))
+        .enumerate()
+        .for_each(|(i, (out_pixel, in_pixel))| {
+            let (slope_x, slope_y) = (gx[i] / 8.0, gy[i] / 8.0);
+            let shade = (-slope_x * light[0] - slope_y * light[1] + light[2]) / (slope_x * slope_x + slope_y * slope_y + 1.0).sqrt();
+            let relief = (0.5 + shade.max(0.0) - light[2]).clamp(0.0, 1.0);
+
+            match output {
+                EmbossOutput::Relief => out_pixel.fill((relief * 255.0).round() as u8),
+                EmbossOutput::Color => {
+                    for channel in 0..3 {
+                        let base = in_pixel[channel] as f32 / 255.0;
+                        let blended = if base < 0.5 {
+                            2.0 * base * relief
+                        } else {
+                            1.0 - 2.0 * (1.0 - base) * (1.0 - relief)
+                        };
+                        out_pixel[channel] = (blended * 255.0).round().clamp(0.0, 255.0) as u8;
+                    }
+                }
+            }
+        });
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+const CARTOON_SPATIAL_SIGMA: f32 = 3.0;
+
+// Smooths flat regions with the bilateral grid, posterizes them to `levels` per channel and draws
+// dark outlines wherever the Sobel magnitude of the smoothed image passes `edge_threshold`.
+pub fn cartoon(img: &DynamicImage, smoothing: f32, levels: u32, edge_threshold: f32, edge_thickness: u32, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    send_progress(&progress_tx, 0.0);
+
+    let smoothed = bilateral(img, CARTOON_SPATIAL_SIGMA, smoothing, true, None);
+    let (width, height) = smoothed.dimensions();
+
+    send_progress(&progress_tx, 0.4);
+
+    let heights: Vec<f32> = smoothed.as_raw().par_chunks_exact(3).map(luma).collect();
+    let (gx, gy) = gradients(&heights, width, height, GradientKernel::SOBEL);
+    // A full black-to-white step gives a Sobel response of 4 * 255
+    let edge_pixels: Vec<u8> = gx
+        .par_iter()
+        .zip(gy.par_iter())
+        .flat_map_iter(|(&x, &y)| [if x.hypot(y) / 4.0 > edge_threshold { 255 } else { 0 }; 3])
+        .collect();
+    let mut edges = ImageBuffer::from_vec(width, height, edge_pixels).unwrap();
+    if edge_thickness > 1 {
+        edges = morphology(&DynamicImage::ImageRgb8(edges), MorphOperation::Dilate, StructuringElement::Disk, edge_thickness - 1, None);
+    }
+
+    send_progress(&progress_tx, 0.7);
+
+    let step = 255.0 / (levels.max(2) - 1) as f32;
+    let mut out_buffer = ImageBuffer::new(width, height);
+    out_buffer
+        .as_mut()
+        .par_chunks_exact_mut(3)
+        .zip(smoothed.as_raw().par_chunks_exact(3))
+        .zip(edges.as_raw().par_chunks_exact(3))
+        .for_each(|((out_pixel, in_pixel), edge)| {
+            if edge[0] > 0 {
+                out_pixel.fill(0);
+                return;
+            }
+            for channel in 0..3 {
+                out_pixel[channel] = ((in_pixel[channel] as f32 / step).round() * step) as u8;
+            }
+        });
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+// Classic color-dodge sketch: the grayscale image divided by its blurred negative. `stroke_width` is
+// that blur's sigma and sets the line thickness; small values keep fine detail. `shading` (0-1)
+// multiplies the original tones back in.
+pub fn pencil_sketch(img: &DynamicImage, stroke_width: f32, shading: f32, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+
+    send_progress(&progress_tx, 0.0);
+
+    let gray: Vec<f32> = rgb_img.as_raw().par_chunks_exact(3).map(luma).collect();
+    let inverted: Vec<u8> = gray.par_iter().flat_map_iter(|&value| [255 - value.round() as u8; 3]).collect();
+    let blurred = gaussian_blur(&DynamicImage::ImageRgb8(ImageBuffer::from_vec(width, height, inverted).unwrap()), stroke_width, false, None);
+
+    send_progress(&progress_tx, 0.5);
+
+    let shading = shading.clamp(0.0, 1.0);
+    let mut out_buffer = ImageBuffer::new(width, height);
+    out_buffer
+        .as_mut()
+        .par_chunks_exact_mut(3)
+        .zip(blurred.as_raw().par_chunks_exact(3))
+        .zip(gray.par_iter())
+        .for_each(|((out_pixel, blurred_pixel), &value)| {
+            let dodged = (value * 255.0 / (256.0 - blurred_pixel[0] as f32)).min(255.0);
+            let shaded = dodged * (1.0 - shading + shading * value / 255.0);
+            out_pixel.fill(shaded.round() as u8);
+        });
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+// Simulates a print screen: each ink is laid down as dots on its own grid of `cell_size` pixels,
+// rotated to the ink's screen angle, with dot area following the ink amount at the cell center.
+pub fn halftone(img: &DynamicImage, cell_size: f32, inks: HalftoneInks, shape: DotShape, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let (width, height) = (img.width(), img.height());
+    let cell_size = cell_size.max(1.0);
+
+    send_progress(&progress_tx, 0.0);
+
+    // Pre-blurring stands in for averaging over each cell, so fine detail doesn't alias
+    let smoothed = gaussian_blur(img, (cell_size * 0.35).max(0.1), false, None);
+    let pixels: Vec<f32> = smoothed.as_raw().par_iter().map(|&value| value as f32 / 255.0).collect();
+    let round_thresholds = round_dot_thresholds();
+
+    send_progress(&progress_tx, 0.3);
+
+    let screens: Vec<(f32, f32)> = match inks {
+        HalftoneInks::Cmyk(angles) => angles.iter().map(|angle| angle.to_radians().sin_cos()).collect(),
+        HalftoneInks::Single { angle, .. } => vec![angle.to_radians().sin_cos()],
+    };
+
+    let mut out_buffer = ImageBuffer::new(width, height);
+    out_buffer
+        .as_mut()
+        .par_chunks_exact_mut(width as usize * 3)
+        .enumerate()
+        .for_each(|(y, out_row)| {
+            for x in 0..width as usize {
+                let mut coverages = [0.0f32; 4];
+                for (ink, &(sin, cos)) in screens.iter().enumerate() {
+                    // Into screen space, snap to the cell center, then back to read the ink amount
+                    let (u, v) = (x as f32 * cos + y as f32 * sin, -(x as f32) * sin + y as f32 * cos);
+                    let (center_u, center_v) = (((u / cell_size).floor() + 0.5) * cell_size, ((v / cell_size).floor() + 0.5) * cell_size);
+                    let (sample_x, sample_y) = (center_u * cos - center_v * sin, center_u * sin + center_v * cos);
+                    let color = sample_bilinear(&pixels, width, height, sample_x, sample_y, BorderMode::Clamp, [0.0; 3]);
+                    let amount = inks.amount(ink, color);
+                    coverages[ink] = dot_coverage(shape, u - center_u, v - center_v, cell_size, amount, &round_thresholds);
+                }
+
+                let out_pixel = &mut out_row[x * 3..x * 3 + 3];
+                match inks {
+                    HalftoneInks::Cmyk(_) => {
+                        // Cyan, magenta and yellow each absorb one primary; black absorbs all three
+                        for channel in 0..3 {
+                            let value = (1.0 - coverages[channel]) * (1.0 - coverages[3]);
+                            out_pixel[channel] = (value * 255.0).round() as u8;
+                        }
+                    }
+                    HalftoneInks::Single { color, .. } => {
+                        for channel in 0..3 {
+                            let value = 255.0 + (color[channel] as f32 - 255.0) * coverages[0];
+                            out_pixel[channel] = value.round() as u8;
+                        }
+                    }
+                }
+            }
+        });
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+// Fraction of the pixel at offset (du, dv) from its cell center that the dot covers, with a
+// one-pixel ramp across the dot edge for anti-aliasing.
+fn dot_coverage(shape: DotShape, du: f32, dv: f32, cell_size: f32, amount: f32, round_thresholds: &[f32]) -> f32 {
+    // Solid and empty cells would otherwise leave half-covered pixels along the cell edges
+    if amount <= 0.0 || amount >= 1.0 {
+        return amount.clamp(0.0, 1.0);
+    }
+
+    let distance = match shape {
+        DotShape::Square => du.abs().max(dv.abs()) - amount.sqrt() * cell_size * 0.5,
+        DotShape::Line => dv.abs() - amount * cell_size * 0.5,
+        DotShape::Round => {
+            // Cosine spot function: dots grow into a checkerboard at 50% and into holes past it
+            let (x, y) = (std::f32::consts::PI * du * 2.0 / cell_size, std::f32::consts::PI * dv * 2.0 / cell_size);
+            let spot = (x.cos() + y.cos()) * 0.5;
+            let slope = (std::f32::consts::PI * 2.0 / cell_size) * 0.5 * x.sin().hypot(y.sin());
+            let threshold = round_thresholds[(amount * 255.0).round() as usize];
+            (threshold - spot) / slope.max(1e-4)
+        }
+    };
+
+    (0.5 - distance).clamp(0.0, 1.0)
+}
+
+
+
+// Spot-function thresholds for each ink level so the inked area of a round-dot cell matches the
+// ink amount, found by sorting the spot values over a sampled cell.
+fn round_dot_thresholds() -> Vec<f32> {
+    const SAMPLES: usize = 64;
+    let mut spots: Vec<f32> = (0..SAMPLES * SAMPLES)
+        .map(|i| {
+            let x = ((i % SAMPLES) as f32 + 0.5) / SAMPLES as f32 * 2.0 - 1.0;
+            let y = ((i / SAMPLES) as f32 + 0.5) / SAMPLES as f32 * 2.0 - 1.0;
+            ((std::f32::consts::PI * x).cos() + (std::f32::consts::PI * y).cos()) * 0.5
+        })
+        .collect();
+    spots.sort_by(|a, b| b.total_cmp(a));
+
+    (0..256)
+        .map(|level| {
+            let inked = (level as f32 / 255.0 * spots.len() as f32).round() as usize;
+            match inked {
+                0 => 1.0,
+                n if n >= spots.len() => -1.0,
+                n => (spots[n - 1] + spots[n]) * 0.5,
+            }
+        })
+        .collect()
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gradient_stops_fill_in_even_positions() {
+        let stops = parse_gradient_stops("#000000, #ff0000@0.25, #ffffff").unwrap();
+        assert_eq!(stops, vec![(0.0, Rgb([0, 0, 0])), (0.25, Rgb([255, 0, 0])), (1.0, Rgb([255, 255, 255]))]);
+    }
+
+    #[test]
+    fn gradient_stops_reject_bad_input() {
+        assert!(parse_gradient_stops("#ff0000").is_err());
+        assert!(parse_gradient_stops("#000000,#zzzzzz@0.5").is_err());
+        assert!(parse_gradient_stops("#000000,#ff0000@2").is_err());
+        assert!(parse_gradient_stops("#000000,#ff0000@half").is_err());
+    }
+}
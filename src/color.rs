@@ -1,9 +1,76 @@
+use std::{str::FromStr, sync::OnceLock};
 use image::{DynamicImage,GenericImageView, ImageBuffer, Rgb};
-use rayon::{iter::{IndexedParallelIterator, ParallelIterator}, slice::{ParallelSlice, ParallelSliceMut}};
-use crate::{ProgressSender, send_progress};
+use rayon::{iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator}, slice::{ParallelSlice, ParallelSliceMut}};
+use crate::{enhancement::apply_luts, ProgressSender, send_progress};
 
 
-pub fn saturate(img: &DynamicImage, factor: f32, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorSpace {
+    Hsl,
+    Oklch,
+    Lch,
+}
+
+impl FromStr for ColorSpace {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "hsl" => Ok(ColorSpace::Hsl),
+            "oklch" => Ok(ColorSpace::Oklch),
+            "lch" => Ok(ColorSpace::Lch),
+            other => Err(format!("Unknown color space: {}", other)),
+        }
+    }
+}
+
+
+
+pub fn saturate(img: &DynamicImage, factor: f32, space: ColorSpace, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    adjust_cylindrical(img, space, progress_tx, |lightness, chroma, hue| {
+        (lightness, chroma * factor, hue)
+    })
+}
+
+
+
+const VIBRANCE_CHROMA_RANGE: f32 = 0.25;
+const SKIN_HUE: f32 = 60.0;
+const SKIN_HUE_WIDTH: f32 = 40.0;
+
+
+
+// Saturation boost weighted toward muted colors, measured as OKLCH chroma. Skin tones sit around
+// OKLCH hue 35-80 degrees, so with `protect_skin` that band is faded out of the boost.
+pub fn vibrance(img: &DynamicImage, amount: f32, protect_skin: bool, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let strength = amount / 100.0;
+
+    adjust_cylindrical(img, ColorSpace::Oklch, progress_tx, |lightness, chroma, hue| {
+        let saturation = (chroma / VIBRANCE_CHROMA_RANGE).min(1.0);
+        let mut boost = strength * (1.0 - saturation);
+
+        if protect_skin && boost > 0.0 {
+            let distance = ((hue - SKIN_HUE + 180.0).rem_euclid(360.0) - 180.0).abs();
+            let t = (1.0 - distance / SKIN_HUE_WIDTH).clamp(0.0, 1.0);
+            let skin_weight = t * t * (3.0 - 2.0 * t);
+            boost *= 1.0 - skin_weight;
+        }
+
+        (lightness, chroma * (1.0 + boost).max(0.0), hue)
+    })
+}
+
+
+
+pub fn lightness(img: &DynamicImage, amount: f32, space: ColorSpace, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    adjust_cylindrical(img, space, progress_tx, |lightness, chroma, hue| {
+        (lightness + amount / 100.0, chroma, hue)
+    })
+}
+
+
+
+pub fn invert(img: &DynamicImage, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
     let rgb_img = img.to_rgb8();
     let (width, height) = rgb_img.dimensions();
     let mut out_buffer = ImageBuffer::new(width, height);
@@ -17,24 +84,38 @@ pub fn saturate(img: &DynamicImage, factor: f32, progress_tx: Option<ProgressSen
         .par_chunks_exact(3)
         .zip(out_pixels.par_chunks_exact_mut(3))
         .for_each(|(in_pixel, out_pixel)| {
-            let (h, s, l) = rgb_to_hsl(in_pixel[0], in_pixel[1], in_pixel[2]);
-            let new_saturation = (s * factor).clamp(0.0, 1.0);
-            let (new_red, new_green, new_blue) = hsl_to_rgb(h, new_saturation, l);
-
-            out_pixel[0] = new_red;
-            out_pixel[1] = new_green;
-            out_pixel[2] = new_blue;
+            out_pixel[0] = 255 - in_pixel[0];
+            out_pixel[1] = 255 - in_pixel[1];
+            out_pixel[2] = 255 - in_pixel[2];
         });
-
     send_progress(&progress_tx, 1.0);
     out_buffer
 }
 
 
 
-pub fn invert(img: &DynamicImage, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+pub fn hue_rotate(img: &DynamicImage, degrees: f32, space: ColorSpace, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    adjust_cylindrical(img, space, progress_tx, |lightness, chroma, hue| {
+        let mut h = (hue + degrees) % 360.0;
+        if h < 0.0 {
+            h += 360.0;
+        }
+        (lightness, chroma, h)
+    })
+}
+
+
+
+// Runs `adjust` on (lightness, chroma, hue) in the chosen space. Lightness is normalized to 0..1 in every space;
+// for HSL the chroma slot holds saturation.
+fn adjust_cylindrical(
+    img: &DynamicImage,
+    space: ColorSpace,
+    progress_tx: Option<ProgressSender>,
+    adjust: impl Fn(f32, f32, f32) -> (f32, f32, f32) + Sync + Send,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
     let rgb_img = img.to_rgb8();
-    let (width, height) = rgb_img.dimensions();
+    let (width, height) = img.dimensions();
     let mut out_buffer = ImageBuffer::new(width, height);
     
     send_progress(&progress_tx, 0.0);
@@ -46,48 +127,558 @@ pub fn invert(img: &DynamicImage, progress_tx: Option<ProgressSender>) -> ImageB
         .par_chunks_exact(3)
         .zip(out_pixels.par_chunks_exact_mut(3))
         .for_each(|(in_pixel, out_pixel)| {
-            out_pixel[0] = 255 - in_pixel[0];
-            out_pixel[1] = 255 - in_pixel[1];
-            out_pixel[2] = 255 - in_pixel[2];
+            let (new_red, new_green, new_blue) = match space {
+                ColorSpace::Hsl => {
+                    let (h, s, l) = rgb_to_hsl(in_pixel[0], in_pixel[1], in_pixel[2]);
+                    let (l, s, h) = adjust(l, s, h);
+                    hsl_to_rgb(h, s.clamp(0.0, 1.0), l.clamp(0.0, 1.0))
+                }
+                ColorSpace::Oklch => {
+                    let (l, a, b) = rgb_to_oklab(in_pixel[0], in_pixel[1], in_pixel[2]);
+                    let (l, c, h) = lab_to_lch(l, a, b);
+                    let (l, c, h) = adjust(l, c, h);
+                    lch_to_rgb_in_gamut(l.clamp(0.0, 1.0), c.max(0.0), h, oklab_to_linear)
+                }
+                ColorSpace::Lch => {
+                    let (l, a, b) = rgb_to_lab(in_pixel[0], in_pixel[1], in_pixel[2]);
+                    let (l, c, h) = lab_to_lch(l / 100.0, a, b);
+                    let (l, c, h) = adjust(l, c, h);
+                    lch_to_rgb_in_gamut(l.clamp(0.0, 1.0), c.max(0.0), h, |l, a, b| lab_to_linear(l * 100.0, a, b))
+                }
+            };
+
+            out_pixel[0] = new_red;
+            out_pixel[1] = new_green;
+            out_pixel[2] = new_blue;
         });
+
     send_progress(&progress_tx, 1.0);
     out_buffer
 }
 
 
 
-pub fn hue_rotate(img: &DynamicImage, degrees: f32, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+pub fn rgb_to_oklab(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
+    let red = srgb_to_linear(r);
+    let green = srgb_to_linear(g);
+    let blue = srgb_to_linear(b);
+
+    let l = (0.41222147 * red + 0.53633254 * green + 0.051445993 * blue).cbrt();
+    let m = (0.2119035 * red + 0.6806995 * green + 0.10739696 * blue).cbrt();
+    let s = (0.08830246 * red + 0.28171884 * green + 0.6299787 * blue).cbrt();
+
+    (
+        0.21045426 * l + 0.7936178 * m - 0.004072047 * s,
+        1.9779985 * l - 2.4285922 * m + 0.4505937 * s,
+        0.025904037 * l + 0.78277177 * m - 0.80867577 * s,
+    )
+}
+
+
+
+pub fn oklab_to_rgb(l: f32, a: f32, b: f32) -> (u8, u8, u8) {
+    let (red, green, blue) = oklab_to_linear(l, a, b);
+    (linear_to_srgb(red), linear_to_srgb(green), linear_to_srgb(blue))
+}
+
+
+
+fn oklab_to_linear(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
+    let l_ = l + 0.39633778 * a + 0.21580376 * b;
+    let m_ = l - 0.10556135 * a - 0.06385417 * b;
+    let s_ = l - 0.08948418 * a - 1.2914855 * b;
+
+    let l3 = l_ * l_ * l_;
+    let m3 = m_ * m_ * m_;
+    let s3 = s_ * s_ * s_;
+
+    (
+        4.0767417 * l3 - 3.3077116 * m3 + 0.23096993 * s3,
+        -1.268438 * l3 + 2.6097574 * m3 - 0.3413194 * s3,
+        -0.0041960863 * l3 - 0.7034186 * m3 + 1.7076147 * s3,
+    )
+}
+
+
+
+const LAB_WHITE: (f32, f32, f32) = (0.95047, 1.0, 1.08883);
+const LAB_DELTA: f32 = 6.0 / 29.0;
+
+
+
+pub fn rgb_to_lab(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
+    let red = srgb_to_linear(r);
+    let green = srgb_to_linear(g);
+    let blue = srgb_to_linear(b);
+
+    let x = 0.4124564 * red + 0.3575761 * green + 0.1804375 * blue;
+    let y = 0.2126729 * red + 0.7151522 * green + 0.0721750 * blue;
+    let z = 0.0193339 * red + 0.119192 * green + 0.9503041 * blue;
+
+    let f = |t: f32| {
+        if t > LAB_DELTA * LAB_DELTA * LAB_DELTA {
+            t.cbrt()
+        } else {
+            t / (3.0 * LAB_DELTA * LAB_DELTA) + 4.0 / 29.0
+        }
+    };
+
+    let fx = f(x / LAB_WHITE.0);
+    let fy = f(y / LAB_WHITE.1);
+    let fz = f(z / LAB_WHITE.2);
+
+    (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+
+
+pub fn lab_to_rgb(l: f32, a: f32, b: f32) -> (u8, u8, u8) {
+    let (red, green, blue) = lab_to_linear(l, a, b);
+    (linear_to_srgb(red), linear_to_srgb(green), linear_to_srgb(blue))
+}
+
+
+
+fn lab_to_linear(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
+    let fy = (l + 16.0) / 116.0;
+    let fx = fy + a / 500.0;
+    let fz = fy - b / 200.0;
+
+    let f_inv = |t: f32| {
+        if t > LAB_DELTA {
+            t * t * t
+        } else {
+            3.0 * LAB_DELTA * LAB_DELTA * (t - 4.0 / 29.0)
+        }
+    };
+
+    let x = f_inv(fx) * LAB_WHITE.0;
+    let y = f_inv(fy) * LAB_WHITE.1;
+    let z = f_inv(fz) * LAB_WHITE.2;
+
+    (
+        3.2404542 * x - 1.5371385 * y - 0.4985314 * z,
+        -0.969266 * x + 1.8760108 * y + 0.0415560 * z,
+        0.0556434 * x - 0.2040259 * y + 1.0572252 * z,
+    )
+}
+
+
+
+// Works for both OKLab and CIELAB: returns (lightness, chroma, hue in degrees).
+pub fn lab_to_lch(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
+    let chroma = (a * a + b * b).sqrt();
+    let mut hue = b.atan2(a).to_degrees();
+    if hue < 0.0 {
+        hue += 360.0;
+    }
+    (l, chroma, hue)
+}
+
+
+
+pub fn lch_to_lab(l: f32, c: f32, h: f32) -> (f32, f32, f32) {
+    let radians = h.to_radians();
+    (l, c * radians.cos(), c * radians.sin())
+}
+
+
+
+// Out-of-gamut colors keep their lightness and hue; chroma is reduced until they fit in sRGB.
+fn lch_to_rgb_in_gamut(l: f32, c: f32, h: f32, to_linear: impl Fn(f32, f32, f32) -> (f32, f32, f32)) -> (u8, u8, u8) {
+    let in_gamut = |(red, green, blue): (f32, f32, f32)| {
+        let epsilon = 2e-3;
+        [red, green, blue].iter().all(|channel| (-epsilon..=1.0 + epsilon).contains(channel))
+    };
+
+    let (lab_l, a, b) = lch_to_lab(l, c, h);
+    let mut linear = to_linear(lab_l, a, b);
+
+    if !in_gamut(linear) {
+        // The gamut boundary along a chroma ray isn't always monotone, so step down coarsely before refining.
+        let steps = 32;
+        let mut low = 0.0;
+        let mut high = c;
+        for step in 1..=steps {
+            let candidate = c * (1.0 - step as f32 / steps as f32);
+            let (lab_l, a, b) = lch_to_lab(l, candidate, h);
+            if in_gamut(to_linear(lab_l, a, b)) {
+                low = candidate;
+                high = candidate + c / steps as f32;
+                break;
+            }
+        }
+        for _ in 0..8 {
+            let mid = (low + high) * 0.5;
+            let (lab_l, a, b) = lch_to_lab(l, mid, h);
+            if in_gamut(to_linear(lab_l, a, b)) {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        let (lab_l, a, b) = lch_to_lab(l, low, h);
+        linear = to_linear(lab_l, a, b);
+    }
+
+    (linear_to_srgb(linear.0), linear_to_srgb(linear.1), linear_to_srgb(linear.2))
+}
+
+
+
+// Each row produces one output channel as r, g, b weights plus an offset in 0-255 units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelMatrix {
+    pub rows: [[f32; 4]; 3],
+}
+
+impl ChannelMatrix {
+    pub const IDENTITY: ChannelMatrix = ChannelMatrix {
+        rows: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0]],
+    };
+    pub const SEPIA: ChannelMatrix = ChannelMatrix {
+        rows: [[0.393, 0.769, 0.189, 0.0], [0.349, 0.686, 0.168, 0.0], [0.272, 0.534, 0.131, 0.0]],
+    };
+    pub const LUMA_BW: ChannelMatrix = ChannelMatrix::monochrome(0.299, 0.587, 0.114);
+    pub const RED_FILTER_BW: ChannelMatrix = ChannelMatrix::monochrome(1.2, 0.4, -0.6);
+    pub const GREEN_FILTER_BW: ChannelMatrix = ChannelMatrix::monochrome(-0.2, 1.4, -0.2);
+    pub const BLUE_FILTER_BW: ChannelMatrix = ChannelMatrix::monochrome(-0.2, 0.2, 1.0);
+
+    pub const fn monochrome(red: f32, green: f32, blue: f32) -> ChannelMatrix {
+        let row = [red, green, blue, 0.0];
+        ChannelMatrix { rows: [row, row, row] }
+    }
+
+    pub fn preset(name: &str) -> Option<ChannelMatrix> {
+        match name {
+            "identity" => Some(ChannelMatrix::IDENTITY),
+            "sepia" => Some(ChannelMatrix::SEPIA),
+            "luma-bw" => Some(ChannelMatrix::LUMA_BW),
+            "red-filter-bw" => Some(ChannelMatrix::RED_FILTER_BW),
+            "green-filter-bw" => Some(ChannelMatrix::GREEN_FILTER_BW),
+            "blue-filter-bw" => Some(ChannelMatrix::BLUE_FILTER_BW),
+            _ => None,
+        }
+    }
+}
+
+// Accepts a preset name, 9 numbers (3x3, no offsets) or 12 numbers (3 rows of r, g, b, offset), row by row.
+impl FromStr for ChannelMatrix {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let value = value.trim().to_lowercase();
+        if let Some(matrix) = ChannelMatrix::preset(&value) {
+            return Ok(matrix);
+        }
+
+        let numbers = value
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .map(|part| part.parse::<f32>().map_err(|_| format!("Invalid matrix value: {}", part)))
+            .collect::<std::result::Result<Vec<f32>, String>>()?;
+
+        let columns = match numbers.len() {
+            9 => 3,
+            12 => 4,
+            count => return Err(format!("Channel matrix needs 9 or 12 values or a preset name, got {}", count)),
+        };
+
+        let mut rows = [[0.0f32; 4]; 3];
+        for (row, values) in rows.iter_mut().zip(numbers.chunks_exact(columns)) {
+            row[..columns].copy_from_slice(values);
+        }
+        Ok(ChannelMatrix { rows })
+    }
+}
+
+
+
+pub fn channel_mixer(img: &DynamicImage, matrix: &ChannelMatrix, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
     let rgb_img = img.to_rgb8();
-    let (width, height) = img.dimensions();
+    let (width, height) = rgb_img.dimensions();
     let mut out_buffer = ImageBuffer::new(width, height);
     
     send_progress(&progress_tx, 0.0);
 
     let in_pixels = rgb_img.as_raw();
     let out_pixels = out_buffer.as_mut();
+    let rows = matrix.rows;
 
     in_pixels
         .par_chunks_exact(3)
         .zip(out_pixels.par_chunks_exact_mut(3))
         .for_each(|(in_pixel, out_pixel)| {
-            let (mut h, s, l) = rgb_to_hsl(in_pixel[0], in_pixel[1], in_pixel[2]);
+            let red = in_pixel[0] as f32;
+            let green = in_pixel[1] as f32;
+            let blue = in_pixel[2] as f32;
 
-            h = (h + degrees) % 360.0;
-            if h < 0.0 {
-                h += 360.0;
+            for (out_value, row) in out_pixel.iter_mut().zip(rows.iter()) {
+                let mixed = (red * row[0]) + (green * row[1]) + (blue * row[2]) + row[3];
+                *out_value = mixed.clamp(0.0, 255.0) as u8;
             }
+        });
 
-            let (new_red, new_green, new_blue) = hsl_to_rgb(h, s, l);
-            out_pixel[0] = new_red;
-            out_pixel[1] = new_green;
-            out_pixel[2] = new_blue;
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
 
-        });
+
+
+pub fn channel_adjust(
+    img: &DynamicImage,
+    brightness: [i32; 3],
+    contrast: [f32; 3],
+    gamma: [f32; 3],
+    progress_tx: Option<ProgressSender>,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+
+    send_progress(&progress_tx, 0.0);
+
+    let mut luts = [[0u8; 256]; 3];
+    for (channel, lut) in luts.iter_mut().enumerate() {
+        let inv_gamma = 1.0 / gamma[channel].max(0.01);
+        for (value, entry) in lut.iter_mut().enumerate() {
+            let shifted = value as f32 + brightness[channel] as f32;
+            let contrasted = contrast[channel] * (shifted - 128.0) + 128.0;
+            let normalized = (contrasted / 255.0).clamp(0.0, 1.0);
+            *entry = (normalized.powf(inv_gamma) * 255.0).round() as u8;
+        }
+    }
+
+    let out_buffer = apply_luts(&rgb_img, &luts);
 
     send_progress(&progress_tx, 1.0);
     out_buffer
 }
 
+
+
+// Parses "#rrggbb", "rrggbb" or the short "#rgb" form.
+pub fn parse_hex_color(value: &str) -> crate::Result<Rgb<u8>> {
+    let hex = value.trim().trim_start_matches('#');
+    // Checked up front so the byte slicing below can't land inside a multibyte character
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid color '{}', expected #rrggbb", value).into());
+    }
+    let expanded: String = match hex.len() {
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 => hex.to_string(),
+        _ => return Err(format!("Invalid color '{}', expected #rrggbb", value).into()),
+    };
+
+    let channel = |index: usize| u8::from_str_radix(&expanded[index..index + 2], 16)
+        .map_err(|_| format!("Invalid color '{}', expected #rrggbb", value));
+    Ok(Rgb([channel(0)?, channel(2)?, channel(4)?]))
+}
+
+
+
+pub enum WhiteBalance {
+    Manual { temperature: f32, tint: f32 },
+    GrayWorld,
+    WhitePatch,
+    GrayPoint { x: u32, y: u32 },
+}
+
+
+
+pub fn white_balance(img: &DynamicImage, mode: WhiteBalance, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+
+    send_progress(&progress_tx, 0.0);
+
+    let in_pixels = rgb_img.as_raw();
+
+    let gains = match mode {
+        WhiteBalance::Manual { temperature, tint } => {
+            let (ref_red, ref_green, ref_blue) = kelvin_to_rgb(6500.0);
+            let (red, green, blue) = kelvin_to_rgb(temperature);
+            let tint_gain = 1.0 - tint.clamp(-100.0, 100.0) / 250.0;
+            let gains = [ref_red / red, ref_green / green * tint_gain, ref_blue / blue];
+
+            let luma_gain = 0.299 * gains[0] + 0.587 * gains[1] + 0.114 * gains[2];
+            gains.map(|gain| gain / luma_gain)
+        }
+        WhiteBalance::GrayWorld => {
+            let sums = in_pixels
+                .par_chunks_exact(3)
+                .fold(|| [0u64; 3], |mut sums, pixel| {
+                    sums[0] += pixel[0] as u64;
+                    sums[1] += pixel[1] as u64;
+                    sums[2] += pixel[2] as u64;
+                    sums
+                })
+                .reduce(|| [0u64; 3], |a, b| [a[0] + b[0], a[1] + b[1], a[2] + b[2]]);
+
+            let means = sums.map(|sum| (sum as f32).max(1.0));
+            let gray = (means[0] + means[1] + means[2]) / 3.0;
+            [gray / means[0], gray / means[1], gray / means[2]]
+        }
+        WhiteBalance::WhitePatch => {
+            let histograms = in_pixels
+                .par_chunks_exact(3)
+                .fold(|| [[0u32; 256]; 3], |mut histograms, pixel| {
+                    histograms[0][pixel[0] as usize] += 1;
+                    histograms[1][pixel[1] as usize] += 1;
+                    histograms[2][pixel[2] as usize] += 1;
+                    histograms
+                })
+                .reduce(|| [[0u32; 256]; 3], |mut a, b| {
+                    for channel in 0..3 {
+                        for value in 0..256 {
+                            a[channel][value] += b[channel][value];
+                        }
+                    }
+                    a
+                });
+
+            // The 99.5th percentile stands in for the brightest white so a few clipped highlights don't decide the result.
+            let cutoff = (width as u64 * height as u64) / 200;
+            histograms.map(|histogram| {
+                let mut above = 0u64;
+                let mut white = 255usize;
+                for value in (0..256).rev() {
+                    above += histogram[value] as u64;
+                    if above > cutoff {
+                        white = value;
+                        break;
+                    }
+                }
+                255.0 / white.max(1) as f32
+            })
+        }
+        WhiteBalance::GrayPoint { x, y } => {
+            let center_x = x.min(width.saturating_sub(1));
+            let center_y = y.min(height.saturating_sub(1));
+
+            let mut sums = [0f32; 3];
+            let mut count = 0.0;
+            for sample_y in center_y.saturating_sub(2)..=(center_y + 2).min(height.saturating_sub(1)) {
+                for sample_x in center_x.saturating_sub(2)..=(center_x + 2).min(width.saturating_sub(1)) {
+                    let idx = ((sample_y * width + sample_x) * 3) as usize;
+                    sums[0] += in_pixels[idx] as f32;
+                    sums[1] += in_pixels[idx + 1] as f32;
+                    sums[2] += in_pixels[idx + 2] as f32;
+                    count += 1.0;
+                }
+            }
+
+            let means = sums.map(|sum| (sum / count).max(1.0));
+            let gray = (means[0] + means[1] + means[2]) / 3.0;
+            [gray / means[0], gray / means[1], gray / means[2]]
+        }
+    };
+
+    send_progress(&progress_tx, 0.5);
+
+    let luts = gains.map(|gain| {
+        let mut lut = [0u8; 256];
+        for (value, entry) in lut.iter_mut().enumerate() {
+            *entry = (value as f32 * gain).round().clamp(0.0, 255.0) as u8;
+        }
+        lut
+    });
+
+    let out_buffer = apply_luts(&rgb_img, &luts);
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+// Approximate sRGB color of a blackbody radiator (Tanner Helland's fit), valid from roughly 1000K to 40000K.
+fn kelvin_to_rgb(kelvin: f32) -> (f32, f32, f32) {
+    let temp = kelvin.clamp(1000.0, 40000.0) / 100.0;
+
+    let red = if temp <= 66.0 {
+        255.0
+    } else {
+        329.69873 * (temp - 60.0).powf(-0.13320476)
+    };
+
+    let green = if temp <= 66.0 {
+        99.4708 * temp.ln() - 161.11957
+    } else {
+        288.12216 * (temp - 60.0).powf(-0.075514846)
+    };
+
+    let blue = if temp >= 66.0 {
+        255.0
+    } else if temp <= 19.0 {
+        0.0
+    } else {
+        138.51773 * (temp - 10.0).ln() - 305.0448
+    };
+
+    (red.clamp(1.0, 255.0), green.clamp(1.0, 255.0), blue.clamp(1.0, 255.0))
+}
+
+
+
+static SRGB_TO_LINEAR: OnceLock<[f32; 256]> = OnceLock::new();
+static LINEAR_TO_SRGB: OnceLock<Vec<u8>> = OnceLock::new();
+const LINEAR_LUT_SIZE: usize = 4096;
+
+
+
+#[inline(always)]
+pub fn srgb_to_linear(value: u8) -> f32 {
+    SRGB_TO_LINEAR.get_or_init(|| {
+        let mut lut = [0.0f32; 256];
+        for (value, entry) in lut.iter_mut().enumerate() {
+            let encoded = value as f32 / 255.0;
+            *entry = if encoded <= 0.04045 {
+                encoded / 12.92
+            } else {
+                ((encoded + 0.055) / 1.055).powf(2.4)
+            };
+        }
+        lut
+    })[value as usize]
+}
+
+
+
+#[inline(always)]
+pub fn linear_to_srgb(value: f32) -> u8 {
+    let lut = LINEAR_TO_SRGB.get_or_init(|| {
+        (0..LINEAR_LUT_SIZE)
+            .map(|index| {
+                let linear = index as f32 / (LINEAR_LUT_SIZE - 1) as f32;
+                let encoded = if linear <= 0.0031308 {
+                    linear * 12.92
+                } else {
+                    1.055 * linear.powf(1.0 / 2.4) - 0.055
+                };
+                (encoded * 255.0).round().clamp(0.0, 255.0) as u8
+            })
+            .collect()
+    });
+    lut[(value.clamp(0.0, 1.0) * (LINEAR_LUT_SIZE - 1) as f32).round() as usize]
+}
+
+
+
+pub fn to_linear(rgb_img: &ImageBuffer<Rgb<u8>, Vec<u8>>) -> ImageBuffer<Rgb<f32>, Vec<f32>> {
+    let (width, height) = rgb_img.dimensions();
+    let pixels = rgb_img.as_raw().par_iter().map(|&value| srgb_to_linear(value)).collect();
+    ImageBuffer::from_vec(width, height, pixels).unwrap()
+}
+
+
+
+pub fn from_linear(linear_img: &ImageBuffer<Rgb<f32>, Vec<f32>>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let (width, height) = linear_img.dimensions();
+    let pixels = linear_img.as_raw().par_iter().map(|&value| linear_to_srgb(value)).collect();
+    ImageBuffer::from_vec(width, height, pixels).unwrap()
+}
+
+
+
 #[inline(always)]
 fn rgb_to_hsl(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
     let red_normal = r as f32 * ( 1.0 / 255.0);
@@ -149,4 +740,28 @@ fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
         ((green + m) * 255.0) as u8,
         ((blue + m) * 255.0) as u8,
     )
-}
\ No newline at end of file
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_matrix_accepts_presets_and_value_lists() {
+        assert!("sepia".parse::<ChannelMatrix>().is_ok());
+        let matrix: ChannelMatrix = "0 0 1, 0 1 0, 1 0 0".parse().unwrap();
+        assert_eq!(matrix.rows[0], [0.0, 0.0, 1.0, 0.0]);
+        let matrix: ChannelMatrix = "1 0 0 10 0 1 0 0 0 0 1 -5".parse().unwrap();
+        assert_eq!(matrix.rows[2], [0.0, 0.0, 1.0, -5.0]);
+    }
+
+    #[test]
+    fn channel_matrix_rejects_bad_input() {
+        assert!("1 0 0 0 1 0 0 0".parse::<ChannelMatrix>().is_err());
+        assert!("1 0 0 0 1 0 0 0 1 0".parse::<ChannelMatrix>().is_err());
+        assert!("1 0 0 0 x 0 0 0 1".parse::<ChannelMatrix>().is_err());
+        assert!("not-a-preset".parse::<ChannelMatrix>().is_err());
+    }
+}
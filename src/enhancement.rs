@@ -1,11 +1,160 @@
-use std::sync::{Arc};
-use image::{imageops::blur, DynamicImage, ImageBuffer, Rgb};
-use rayon::{iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator}, slice::{ParallelSlice, ParallelSliceMut}};
-use crate::{ProgressSender, send_progress};
+use std::{collections::{HashMap, HashSet}, str::FromStr, sync::Arc};
+use image::{imageops::{blur, crop_imm, resize, FilterType}, DynamicImage, GrayImage, ImageBuffer, Luma, Rgb};
+use rayon::{iter::{IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator}, slice::{ParallelSlice, ParallelSliceMut}};
+use crate::{color::{from_linear, linear_to_srgb, srgb_to_linear, to_linear}, BorderMode, ProgressSender, send_progress};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EdgeOperator {
+    Sobel,
+    Scharr,
+    Prewitt,
+    LaplacianOfGaussian { sigma: f32, threshold: f32 },
+    Canny { sigma: f32, low: f32, high: f32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EdgeOutput {
+    Magnitude,
+    Direction,
+    Overlay(Rgb<u8>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MorphOperation {
+    Erode,
+    Dilate,
+    Open,
+    Close,
+    TopHat,
+    BlackHat,
+    Gradient,
+}
+
+impl FromStr for MorphOperation {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "erode" => Ok(MorphOperation::Erode),
+            "dilate" => Ok(MorphOperation::Dilate),
+            "open" => Ok(MorphOperation::Open),
+            "close" => Ok(MorphOperation::Close),
+            "top-hat" => Ok(MorphOperation::TopHat),
+            "black-hat" => Ok(MorphOperation::BlackHat),
+            "gradient" => Ok(MorphOperation::Gradient),
+            other => Err(format!("Unknown morphological operation: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StructuringElement {
+    Square,
+    Cross,
+    Disk,
+}
+
+impl FromStr for StructuringElement {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "square" => Ok(StructuringElement::Square),
+            "cross" => Ok(StructuringElement::Cross),
+            "disk" => Ok(StructuringElement::Disk),
+            other => Err(format!("Unknown structuring element: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThresholdMethod {
+    Otsu,
+    Mean,
+    Gaussian,
+    Sauvola,
+}
+
+impl FromStr for ThresholdMethod {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "otsu" => Ok(ThresholdMethod::Otsu),
+            "mean" => Ok(ThresholdMethod::Mean),
+            "gaussian" => Ok(ThresholdMethod::Gaussian),
+            "sauvola" => Ok(ThresholdMethod::Sauvola),
+            other => Err(format!("Unknown threshold method: {}", other)),
+        }
+    }
+}
+
+// Convolution kernel stored row by row
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kernel {
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<f32>,
+}
+
+impl Kernel {
+    // Reads the kernel from a file when `spec` names one, otherwise parses it inline.
+    pub fn from_spec(spec: &str) -> crate::Result<Kernel> {
+        if std::path::Path::new(spec).is_file() {
+            Kernel::parse(&std::fs::read_to_string(spec)?)
+        } else {
+            Kernel::parse(spec)
+        }
+    }
+
+    // Rows are separated by ';' or newlines, values by spaces or commas, e.g. "1 2 1; 2 4 2; 1 2 1".
+    pub fn parse(source: &str) -> crate::Result<Kernel> {
+        let rows: Vec<Vec<f32>> = source
+            .split([';', '\n'])
+            .map(|row| row.split('#').next().unwrap_or(""))
+            .filter(|row| !row.trim().is_empty())
+            .map(|row| {
+                row.split(|c: char| c.is_whitespace() || c == ',')
+                    .filter(|value| !value.is_empty())
+                    .map(|value| value.parse::<f32>().map_err(|_| format!("Invalid kernel value '{}'", value).into()))
+                    .collect::<crate::Result<Vec<f32>>>()
+            })
+            .collect::<crate::Result<_>>()?;
+
+        let width = rows.first().map_or(0, |row| row.len());
+        if width == 0 {
+            return Err("Kernel is empty".into());
+        }
+        if rows.iter().any(|row| row.len() != width) {
+            return Err("Every kernel row needs the same number of values".into());
+        }
+
+        Ok(Kernel { width, height: rows.len(), values: rows.concat() })
+    }
+
+    // Splits a rank-1 kernel into a column and a row vector whose outer product reproduces it.
+    fn separable(&self) -> Option<(Vec<f32>, Vec<f32>)> {
+        let (pivot, &largest) = self.values.iter().enumerate().max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))?;
+        if largest == 0.0 {
+            return None;
+        }
+
+        let (pivot_row, pivot_column) = (pivot / self.width, pivot % self.width);
+        let column: Vec<f32> = (0..self.height).map(|y| self.values[y * self.width + pivot_column]).collect();
+        let row: Vec<f32> = (0..self.width).map(|x| self.values[pivot_row * self.width + x] / largest).collect();
+
+        let tolerance = largest.abs() * 1e-5;
+        let rank_one = (0..self.height).all(|y| {
+            (0..self.width).all(|x| (column[y] * row[x] - self.values[y * self.width + x]).abs() <= tolerance)
+        });
+        rank_one.then_some((column, row))
+    }
+}
 
 pub fn brightness(
     img: &DynamicImage,
     value: i32,
+    linear: bool,
     progress_tx: Option<ProgressSender>,
 ) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
     let rgb_img = img.to_rgb8();
@@ -14,6 +163,15 @@ pub fn brightness(
 
 
     send_progress(&progress_tx, 0.0);
+
+    if linear {
+        let offset = value as f32 / 255.0;
+        let lut = linear_lut(|light| light + offset);
+        let out_buffer = apply_luts(&rgb_img, &[lut, lut, lut]);
+        send_progress(&progress_tx, 1.0);
+        return out_buffer;
+    }
+
     let progress_tx = Arc::new(progress_tx);
     let in_pixels = rgb_img.as_raw();
     let out_pixels = out_buffer.as_mut();
@@ -34,13 +192,21 @@ pub fn brightness(
 
 
 
-pub fn contrast(img: &DynamicImage, factor: f32, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+pub fn contrast(img: &DynamicImage, factor: f32, linear: bool, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
     let rgb_img = img.to_rgb8();
     let (width, height) = rgb_img.dimensions();
     let mut out_buffer = ImageBuffer::new(width, height);
     
     send_progress(&progress_tx, 0.0);
 
+    if linear {
+        let pivot = srgb_to_linear(128);
+        let lut = linear_lut(|light| factor * (light - pivot) + pivot);
+        let out_buffer = apply_luts(&rgb_img, &[lut, lut, lut]);
+        send_progress(&progress_tx, 1.0);
+        return out_buffer;
+    }
+
     let progress_tx = Arc::new(progress_tx);
 
 
@@ -62,7 +228,211 @@ pub fn contrast(img: &DynamicImage, factor: f32, progress_tx: Option<ProgressSen
 
 
 
-pub fn box_blur(img: &DynamicImage, radius: u32, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+pub fn levels(
+    img: &DynamicImage,
+    in_black: u8,
+    in_white: u8,
+    gamma: f32,
+    out_black: u8,
+    out_white: u8,
+    progress_tx: Option<ProgressSender>,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+
+    send_progress(&progress_tx, 0.0);
+
+    let in_range = (in_white as f32 - in_black as f32).max(1.0);
+    let out_range = out_white as f32 - out_black as f32;
+    let inv_gamma = 1.0 / gamma.max(0.01);
+
+    let mut lut = [0u8; 256];
+    for (value, entry) in lut.iter_mut().enumerate() {
+        let normalized = ((value as f32 - in_black as f32) / in_range).clamp(0.0, 1.0);
+        let corrected = normalized.powf(inv_gamma);
+        *entry = (out_black as f32 + corrected * out_range).round().clamp(0.0, 255.0) as u8;
+    }
+
+    let out_buffer = apply_luts(&rgb_img, &[lut, lut, lut]);
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+pub fn curves(
+    img: &DynamicImage,
+    master: &[(f32, f32)],
+    red: &[(f32, f32)],
+    green: &[(f32, f32)],
+    blue: &[(f32, f32)],
+    progress_tx: Option<ProgressSender>,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+
+    send_progress(&progress_tx, 0.0);
+
+    let master_lut = build_curve_lut(master);
+    let compose = |channel: &[(f32, f32)]| -> [u8; 256] {
+        let channel_lut = build_curve_lut(channel);
+        let mut lut = [0u8; 256];
+        for (value, entry) in lut.iter_mut().enumerate() {
+            *entry = master_lut[channel_lut[value] as usize];
+        }
+        lut
+    };
+
+    let luts = [compose(red), compose(green), compose(blue)];
+    let out_buffer = apply_luts(&rgb_img, &luts);
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+// Parses curve control points written as "input:output" pairs separated by commas, e.g. "0:0,128:150,255:255".
+pub fn parse_curve_points(spec: &str) -> crate::Result<Vec<(f32, f32)>> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (input, output) = pair
+                .split_once(':')
+                .ok_or_else(|| format!("Invalid curve point '{}', expected input:output", pair))?;
+            let input: f32 = input.trim().parse()?;
+            let output: f32 = output.trim().parse()?;
+
+            if !(0.0..=255.0).contains(&input) || !(0.0..=255.0).contains(&output) {
+                return Err(format!("Curve point '{}' must be within 0 to 255", pair).into());
+            }
+            Ok((input, output))
+        })
+        .collect()
+}
+
+
+
+// Builds a 256-entry table from control points using monotone cubic (Fritsch-Carlson) interpolation,
+// so the curve never overshoots between points. No points means identity.
+fn build_curve_lut(points: &[(f32, f32)]) -> [u8; 256] {
+    let mut lut = [0u8; 256];
+
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+    sorted.dedup_by(|a, b| a.0 == b.0);
+
+    if sorted.is_empty() {
+        for (value, entry) in lut.iter_mut().enumerate() {
+            *entry = value as u8;
+        }
+        return lut;
+    }
+
+    if sorted.len() == 1 {
+        lut.fill(sorted[0].1.round().clamp(0.0, 255.0) as u8);
+        return lut;
+    }
+
+    let n = sorted.len();
+    let secants: Vec<f32> = sorted
+        .windows(2)
+        .map(|w| (w[1].1 - w[0].1) / (w[1].0 - w[0].0))
+        .collect();
+
+    let mut tangents = vec![0.0f32; n];
+    tangents[0] = secants[0];
+    tangents[n - 1] = secants[n - 2];
+    for k in 1..n - 1 {
+        tangents[k] = if secants[k - 1] * secants[k] <= 0.0 {
+            0.0
+        } else {
+            (secants[k - 1] + secants[k]) * 0.5
+        };
+    }
+
+    for k in 0..n - 1 {
+        if secants[k] == 0.0 {
+            tangents[k] = 0.0;
+            tangents[k + 1] = 0.0;
+            continue;
+        }
+        let alpha = tangents[k] / secants[k];
+        let beta = tangents[k + 1] / secants[k];
+        let length = alpha * alpha + beta * beta;
+        if length > 9.0 {
+            let scale = 3.0 / length.sqrt();
+            tangents[k] = scale * alpha * secants[k];
+            tangents[k + 1] = scale * beta * secants[k];
+        }
+    }
+
+    let mut segment = 0;
+    for (value, entry) in lut.iter_mut().enumerate() {
+        let x = value as f32;
+
+        let y = if x <= sorted[0].0 {
+            sorted[0].1
+        } else if x >= sorted[n - 1].0 {
+            sorted[n - 1].1
+        } else {
+            while x > sorted[segment + 1].0 {
+                segment += 1;
+            }
+            let (x0, y0) = sorted[segment];
+            let (x1, y1) = sorted[segment + 1];
+            let h = x1 - x0;
+            let t = (x - x0) / h;
+            let t2 = t * t;
+            let t3 = t2 * t;
+
+            (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+                + (t3 - 2.0 * t2 + t) * h * tangents[segment]
+                + (-2.0 * t3 + 3.0 * t2) * y1
+                + (t3 - t2) * h * tangents[segment + 1]
+        };
+
+        *entry = y.round().clamp(0.0, 255.0) as u8;
+    }
+
+    lut
+}
+
+
+
+// Builds a table that decodes each sRGB value to linear light, applies `adjust`, and encodes the result back.
+pub(crate) fn linear_lut(adjust: impl Fn(f32) -> f32) -> [u8; 256] {
+    let mut lut = [0u8; 256];
+    for (value, entry) in lut.iter_mut().enumerate() {
+        *entry = linear_to_srgb(adjust(srgb_to_linear(value as u8)));
+    }
+    lut
+}
+
+
+
+pub(crate) fn apply_luts(rgb_img: &ImageBuffer<Rgb<u8>, Vec<u8>>, luts: &[[u8; 256]; 3]) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let (width, height) = rgb_img.dimensions();
+    let mut out_buffer = ImageBuffer::new(width, height);
+
+    let in_pixels = rgb_img.as_raw();
+    let out_pixels = out_buffer.as_mut();
+
+    in_pixels
+        .par_chunks_exact(3)
+        .zip(out_pixels.par_chunks_exact_mut(3))
+        .for_each(|(in_pixel, out_pixel)| {
+            out_pixel[0] = luts[0][in_pixel[0] as usize];
+            out_pixel[1] = luts[1][in_pixel[1] as usize];
+            out_pixel[2] = luts[2][in_pixel[2] as usize];
+        });
+
+    out_buffer
+}
+
+
+
+pub fn box_blur(img: &DynamicImage, radius: u32, linear: bool, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
     
     let rgb_img = img.to_rgb8();
 
@@ -70,6 +440,21 @@ pub fn box_blur(img: &DynamicImage, radius: u32, progress_tx: Option<ProgressSen
 
     send_progress(&progress_tx, 0.0);
 
+    if linear {
+        let linear_img = to_linear(&rgb_img);
+        let horizontal_blurred = horizontal_box_blur_linear(linear_img.as_raw(), radius, width);
+
+        send_progress(&progress_tx, 0.5);
+
+        let transposed = transpose_rgb(&horizontal_blurred, width, height);
+        let vertical_blurred = horizontal_box_blur_linear(&transposed, radius, height);
+        let final_result = transpose_rgb(&vertical_blurred, height, width);
+
+        send_progress(&progress_tx, 1.0);
+
+        return from_linear(&ImageBuffer::from_vec(width, height, final_result).unwrap());
+    }
+
     let horizontal_blurred = horizontal_box_blur(&rgb_img, radius, width, height);
 
     send_progress(&progress_tx, 0.5);
@@ -145,6 +530,65 @@ fn horizontal_box_blur(img: &ImageBuffer<Rgb<u8>, Vec<u8>>, radius: u32, width:
 
 
 
+fn horizontal_box_blur_linear(pixels: &[f32], radius: u32, width: u32) -> Vec<f32> {
+    let mut result = vec![0.0f32; pixels.len()];
+    let radius = radius as usize;
+    let width = width as usize;
+
+    result.par_chunks_exact_mut(width * 3).enumerate().for_each(|(y, row)| {
+        let in_row = &pixels[y * width * 3..(y + 1) * width * 3];
+        let mut sums = [0.0f32; 3];
+
+        for kx in 0..=radius.min(width - 1) {
+            for channel in 0..3 {
+                sums[channel] += in_row[kx * 3 + channel];
+            }
+        }
+
+        for x in 0..width {
+            let x_min = x.saturating_sub(radius);
+            let x_max = (x + radius).min(width - 1);
+            let window_size = (x_max - x_min + 1) as f32;
+
+            for channel in 0..3 {
+                row[x * 3 + channel] = sums[channel] / window_size;
+            }
+
+            if x + radius + 1 < width {
+                for channel in 0..3 {
+                    sums[channel] += in_row[(x + radius + 1) * 3 + channel];
+                }
+            }
+            if x >= radius {
+                for channel in 0..3 {
+                    sums[channel] -= in_row[(x - radius) * 3 + channel];
+                }
+            }
+        }
+    });
+
+    result
+}
+
+
+
+fn transpose_rgb<T: Copy + Default + Send + Sync>(pixels: &[T], width: u32, height: u32) -> Vec<T> {
+    let mut result = vec![T::default(); pixels.len()];
+    let width = width as usize;
+    let height = height as usize;
+
+    result.par_chunks_exact_mut(height * 3).enumerate().for_each(|(x, out_row)| {
+        for y in 0..height {
+            let in_idx = (y * width + x) * 3;
+            out_row[y * 3..y * 3 + 3].copy_from_slice(&pixels[in_idx..in_idx + 3]);
+        }
+    });
+
+    result
+}
+
+
+
 fn vertical_box_blur(pixels: &[u8], radius: u32, width: u32, height: u32) -> Vec<u8> {
     let result = vec![0u8; (width * height * 3) as usize];
 
@@ -209,8 +653,17 @@ fn vertical_box_blur(pixels: &[u8], radius: u32, width: u32, height: u32) -> Vec
 
 
 
-pub fn gaussian_blur(img: &DynamicImage, sigma: f32, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+pub fn gaussian_blur(img: &DynamicImage, sigma: f32, linear: bool, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
     send_progress(&progress_tx, 0.0);
+
+    if linear {
+        let linear_img = to_linear(&img.to_rgb8());
+        let blurred = blur(&linear_img, sigma);
+        let result = from_linear(&blurred);
+
+        send_progress(&progress_tx, 1.0);
+        return result;
+    }
     
     let blurred = blur(img, sigma);
     let result = DynamicImage::ImageRgba8(blurred).to_rgb8();
@@ -221,75 +674,111 @@ pub fn gaussian_blur(img: &DynamicImage, sigma: f32, progress_tx: Option<Progres
 
 
 
-pub fn sharpen(img: &DynamicImage, strenght: f32, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
-    
-    let rgb_img = img.to_rgb8();
+// Averages along a `length` pixel line through each pixel at `angle` degrees (0 = horizontal).
+pub fn motion_blur(img: &DynamicImage, angle: f32, length: u32, linear: bool, border: BorderMode, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let (direction_x, direction_y) = (angle.to_radians().cos(), -angle.to_radians().sin());
+    let samples = (length.max(1) as usize).min(MAX_PATH_SAMPLES);
+    let extent = (length.max(1) - 1) as f32;
 
-    let (width, height) = rgb_img.dimensions();
-    let mut out_buffer = ImageBuffer::new(width, height);
+    path_blur(img, linear, border, progress_tx, |_, _| samples, move |x, y, t| {
+        let offset = (t - 0.5) * extent;
+        (x + direction_x * offset, y + direction_y * offset)
+    })
+}
 
-    send_progress(&progress_tx, 0.0);
 
-    let in_pixels = rgb_img.as_raw();
-    
-    out_buffer.as_mut().par_chunks_exact_mut((width * 3) as usize)
-        .enumerate()
-        .for_each(|(y, out_row)| {
-            if y == 0 || y == height as usize - 1 {
-                let src_start = (y * width as usize * 3) as usize;
-                out_row.copy_from_slice(&in_pixels[src_start..src_start + (width * 3) as usize]);
-                return;
-            }
-            
-            let row_stride = (width * 3) as usize;
-            
-            for x in 0..width {
-                if x == 0 || x == width - 1 {
-                
-        
-                    let dst_idx = (x * 3) as usize;
-                    out_row[dst_idx] = in_pixels[(y * width as usize + x as usize) * 3];
-                    out_row[dst_idx + 1] = in_pixels[(y * width as usize + x as usize) * 3 + 1];
-                    out_row[dst_idx + 2] = in_pixels[(y * width as usize + x as usize) * 3 + 2];
-                    continue;
-                }
-                
-                let center_idx = ((y * width as usize + x as usize) * 3) as usize;
 
-                let center_red = in_pixels[center_idx] as f32;
-                let center_green = in_pixels[center_idx + 1] as f32;
-                let center_blue = in_pixels[center_idx + 2] as f32;
+// Spin blur: each pixel is averaged along an arc of `angle` degrees around the center, given as
+// fractions of the image size.
+pub fn radial_blur(img: &DynamicImage, angle: f32, center: (f32, f32), linear: bool, border: BorderMode, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let (center_x, center_y) = (center.0 * img.width() as f32, center.1 * img.height() as f32);
+    let sweep = angle.to_radians();
 
-                let top_red = in_pixels[center_idx - row_stride] as f32;
-                let top_green = in_pixels[center_idx - row_stride + 1] as f32;
-                let top_blue = in_pixels[center_idx - row_stride + 2] as f32;
+    path_blur(img, linear, border, progress_tx, move |x, y| {
+        // One sample per pixel of arc length up to MAX_PATH_SAMPLES
+        let distance = (x - center_x).hypot(y - center_y);
+        ((distance * sweep.abs()).ceil() as usize + 1).min(MAX_PATH_SAMPLES)
+    }, move |x, y, t| {
+        let (sin, cos) = ((t - 0.5) * sweep).sin_cos();
+        let (dx, dy) = (x - center_x, y - center_y);
+        (center_x + dx * cos - dy * sin, center_y + dx * sin + dy * cos)
+    })
+}
 
-                let left_red = in_pixels[center_idx - 3] as f32;
-                let left_green = in_pixels[center_idx - 2] as f32;
-                let left_blue = in_pixels[center_idx - 1] as f32;
 
-                let right_red = in_pixels[center_idx + 3] as f32;
-                let right_green = in_pixels[center_idx + 4] as f32;
-                let right_blue = in_pixels[center_idx + 5] as f32;
 
-                let bottom_red = in_pixels[center_idx + row_stride] as f32;
-                let bottom_green = in_pixels[center_idx + row_stride + 1] as f32;
-                let bottom_blue = in_pixels[center_idx + row_stride + 2] as f32;
+// Averages each pixel along the ray towards the center, covering `strength` (0-1) of the distance.
+pub fn zoom_blur(img: &DynamicImage, strength: f32, center: (f32, f32), linear: bool, border: BorderMode, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let (center_x, center_y) = (center.0 * img.width() as f32, center.1 * img.height() as f32);
+    let strength = strength.clamp(0.0, 1.0);
 
-                let sharpened_red = center_red * 5.0 - (top_red + left_red + right_red + bottom_red);
-                let sharpened_green = center_green * 5.0 - (top_green + left_green + right_green + bottom_green);
-                let sharpened_blue = center_blue * 5.0 - (top_blue + left_blue + right_blue + bottom_blue);
+    path_blur(img, linear, border, progress_tx, move |x, y| {
+        let distance = (x - center_x).hypot(y - center_y);
+        ((distance * strength).ceil() as usize + 1).min(MAX_PATH_SAMPLES)
+    }, move |x, y, t| {
+        let scale = 1.0 - strength * t;
+        (center_x + (x - center_x) * scale, center_y + (y - center_y) * scale)
+    })
+}
 
-                let final_red = center_red + strenght * (sharpened_red - center_red);
-                let final_green = center_green + strenght * (sharpened_green - center_green);
-                let final_blue = center_blue + strenght * (sharpened_blue - center_blue);
 
-                let out_idx = (x * 3) as usize;
-                out_row[out_idx] = final_red.clamp(0.0, 255.0) as u8;
-                out_row[out_idx + 1] = final_green.clamp(0.0, 255.0) as u8;
-                out_row[out_idx + 2] = final_blue.clamp(0.0, 255.0) as u8;
+
+// Caps the taps per pixel for the path blurs. Paths longer than this many pixels get taps spaced
+// more than a pixel apart, which can leave faint ghost copies along very long streaks or arcs.
+const MAX_PATH_SAMPLES: usize = 256;
+
+// Shared by the motion, radial and zoom blurs. `samples` gives the number of taps for a pixel
+// and `path` maps a pixel and t in 0..=1 to the point to read, sampled bilinearly.
+fn path_blur(
+    img: &DynamicImage,
+    linear: bool,
+    border: BorderMode,
+    progress_tx: Option<ProgressSender>,
+    samples: impl Fn(f32, f32) -> usize + Sync,
+    path: impl Fn(f32, f32, f32) -> (f32, f32) + Sync,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+
+    send_progress(&progress_tx, 0.0);
+
+    let pixels: Vec<f32> = if linear {
+        to_linear(&rgb_img).into_raw()
+    } else {
+        rgb_img.as_raw().par_iter().map(|&value| value as f32 / 255.0).collect()
+    };
+    let constant = match border {
+        BorderMode::Constant(color) => color.0.map(|channel| if linear { srgb_to_linear(channel) } else { channel as f32 / 255.0 }),
+        _ => [0.0; 3],
+    };
+
+    let mut result = vec![0.0f32; pixels.len()];
+    result.par_chunks_exact_mut(width as usize * 3).enumerate().for_each(|(y, out_row)| {
+        for x in 0..width as usize {
+            let count = samples(x as f32, y as f32).max(1);
+            let step = if count > 1 { 1.0 / (count - 1) as f32 } else { 0.0 };
+            let mut sums = [0.0f32; 3];
+            for i in 0..count {
+                let (sample_x, sample_y) = path(x as f32, y as f32, i as f32 * step);
+                let value = sample_bilinear(&pixels, width, height, sample_x, sample_y, border, constant);
+                for channel in 0..3 {
+                    sums[channel] += value[channel];
+                }
             }
-        });
+            for channel in 0..3 {
+                out_row[x * 3 + channel] = sums[channel] / count as f32;
+            }
+        }
+    });
+
+    send_progress(&progress_tx, 0.5);
+
+    let out_buffer = if linear {
+        from_linear(&ImageBuffer::from_vec(width, height, result).unwrap())
+    } else {
+        let converted = result.par_iter().map(|&value| (value * 255.0).round().clamp(0.0, 255.0) as u8).collect();
+        ImageBuffer::from_vec(width, height, converted).unwrap()
+    };
 
     send_progress(&progress_tx, 1.0);
     out_buffer
@@ -297,88 +786,1212 @@ pub fn sharpen(img: &DynamicImage, strenght: f32, progress_tx: Option<ProgressSe
 
 
 
-pub fn edge_detection(img: &DynamicImage, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
-    let rgb_img = img.to_rgb8();
-    let (width, height) = rgb_img.dimensions();
-    let mut out_buffer = ImageBuffer::new(width, height);
+pub(crate) fn sample_bilinear(pixels: &[f32], width: u32, height: u32, x: f32, y: f32, border: BorderMode, constant: [f32; 3]) -> [f32; 3] {
+    let (left, top) = (x.floor(), y.floor());
+    let (fx, fy) = (x - left, y - top);
+    let (left, top) = (left as isize, top as isize);
 
-    send_progress(&progress_tx, 0.0);
+    let mut value = [0.0f32; 3];
+    for (dy, weight_y) in [(0, 1.0 - fy), (1, fy)] {
+        if weight_y == 0.0 {
+            continue;
+        }
+        let source_y = border.resolve(top + dy, height as usize);
+        for (dx, weight_x) in [(0, 1.0 - fx), (1, fx)] {
+            if weight_x == 0.0 {
+                continue;
+            }
+            let weight = weight_x * weight_y;
+            let tap = match (border.resolve(left + dx, width as usize), source_y) {
+                (Some(source_x), Some(source_y)) => {
+                    let idx = (source_y * width as usize + source_x) * 3;
+                    [pixels[idx], pixels[idx + 1], pixels[idx + 2]]
+                }
+                _ => constant,
+            };
+            for channel in 0..3 {
+                value[channel] += weight * tap[channel];
+            }
+        }
+    }
 
-    let in_pixels = rgb_img.as_raw();
+    value
+}
 
-    out_buffer.as_mut().par_chunks_exact_mut((width * 3) as usize)
-        .enumerate()
-        .for_each(|(y, out_row)| {
-            let row_stride = (width * 3) as usize;
 
-            if y == 0 || y >= height as usize - 1 {
-                out_row.fill(0);
-                return;
-            }
 
-            for x in 0..width {
-                if x == 0 || x >= width - 1 {
-                    let out_idx = (x * 3) as usize;
-                    out_row[out_idx] = 0;
-                    out_row[out_idx + 1] = 0;
-                    out_row[out_idx + 2] = 0;
-                    continue;
+// Keeps a band in focus and blurs progressively outside it. `focus` is (position, angle, band,
+// falloff): the band is tilted by `angle` degrees, and position and the band sizes are fractions
+// of the image's extent across the band, so position 0-1 sweeps it from edge to edge at any angle.
+pub fn tilt_shift(img: &DynamicImage, focus: (f32, f32, f32, f32), max_sigma: f32, linear: bool, border: BorderMode, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let (position, angle, band, falloff) = focus;
+    let (width, height) = (img.width(), img.height());
+    let (normal_x, normal_y) = (angle.to_radians().sin(), angle.to_radians().cos());
+    let extent = (width as f32 * normal_x).abs() + (height as f32 * normal_y).abs();
+    let (center_x, center_y) = (width as f32 * 0.5, height as f32 * 0.5);
+    let band_center = (position - 0.5) * extent;
+
+    let amounts: Vec<f32> = (0..height)
+        .into_par_iter()
+        .flat_map_iter(|y| {
+            (0..width).map(move |x| {
+                let distance = ((x as f32 - center_x) * normal_x + (y as f32 - center_y) * normal_y - band_center).abs() / extent;
+                let outside = distance - band * 0.5;
+                if falloff <= 0.0 {
+                    return if outside > 0.0 { 1.0 } else { 0.0 };
                 }
+                let t = (outside / falloff).clamp(0.0, 1.0);
+                t * t * (3.0 - 2.0 * t)
+            })
+        })
+        .collect();
 
-                let center_idx = (y * width as usize + x as usize) * 3;
-                let get_gray_fast = |idx: usize| -> f32 {
-                    0.299 * in_pixels[idx] as f32 + 
-                    0.587 * in_pixels[idx + 1] as f32 + 
-                    0.114 * in_pixels[idx + 2] as f32
-                };
-
-                let gx = -get_gray_fast(center_idx - row_stride - 3) + 
-                          get_gray_fast(center_idx - row_stride + 3) -
-                         2.0 * get_gray_fast(center_idx - 3) +
-                         2.0 * get_gray_fast(center_idx + 3) - 
-                          get_gray_fast(center_idx + row_stride - 3) +
-                          get_gray_fast(center_idx + row_stride + 3);
-
-                let gy = -get_gray_fast(center_idx - row_stride - 3) -    
-                         2.0 * get_gray_fast(center_idx - row_stride) -   
-                          get_gray_fast(center_idx - row_stride + 3) +    
-                          get_gray_fast(center_idx + row_stride - 3) +    
-                         2.0 * get_gray_fast(center_idx + row_stride) +   
-                          get_gray_fast(center_idx + row_stride + 3);    
-
-                let magnitude = (gx * gx + gy * gy).sqrt().clamp(0.0, 255.0) as u8;
+    variable_blur(img, &amounts, max_sigma, linear, border, progress_tx)
+}
 
-                let out_idx = (x * 3) as usize;
-                out_row[out_idx] = magnitude;
-                out_row[out_idx + 1] = magnitude;
-                out_row[out_idx + 2] = magnitude;
-            }
-        });
 
-    send_progress(&progress_tx, 1.0);
-    out_buffer
+
+// Blurs each pixel by the matching mask brightness: black stays sharp, white gets `max_sigma`.
+pub fn mask_blur(img: &DynamicImage, mask: &GrayImage, max_sigma: f32, invert: bool, linear: bool, border: BorderMode, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let (width, height) = (img.width(), img.height());
+    let mask = if mask.dimensions() != (width, height) {
+        resize(mask, width, height, FilterType::Triangle)
+    } else {
+        mask.clone()
+    };
+
+    let amounts: Vec<f32> = mask
+        .as_raw()
+        .par_iter()
+        .map(|&value| {
+            let amount = value as f32 / 255.0;
+            if invert { 1.0 - amount } else { amount }
+        })
+        .collect();
+
+    variable_blur(img, &amounts, max_sigma, linear, border, progress_tx)
 }
 
 
 
-pub fn thresholding(img: &DynamicImage, threshold: u8, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+const BLUR_LEVELS: usize = 6;
+
+// Builds a stack of Gaussian blurs up to `max_sigma` and picks, per pixel, between the two levels
+// around its amount (0-1). The levels are blurred on a padded copy so the border mode applies.
+fn variable_blur(img: &DynamicImage, amounts: &[f32], max_sigma: f32, linear: bool, border: BorderMode, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
     let rgb_img = img.to_rgb8();
     let (width, height) = rgb_img.dimensions();
 
-    let mut out_buffer = ImageBuffer::new(width, height);
-
     send_progress(&progress_tx, 0.0);
 
-    let in_pixels = rgb_img.as_raw();
-    let out_pixels = out_buffer.as_mut();
+    let pad = (3.0 * max_sigma).ceil() as u32;
+    let padded = DynamicImage::ImageRgb8(border.pad(&rgb_img, pad));
 
-    in_pixels
-        .par_chunks_exact(3)
-        .zip(out_pixels.par_chunks_exact_mut(3))
+    let mut levels = vec![rgb_img.clone()];
+    for level in 1..BLUR_LEVELS {
+        let sigma = max_sigma * level as f32 / (BLUR_LEVELS - 1) as f32;
+        let blurred = gaussian_blur(&padded, sigma.max(0.1), linear, None);
+        levels.push(crop_imm(&blurred, pad, pad, width, height).to_image());
+        send_progress(&progress_tx, 0.9 * level as f64 / (BLUR_LEVELS - 1) as f64);
+    }
+
+    let mut out_buffer = ImageBuffer::new(width, height);
+    out_buffer
+        .as_mut()
+        .par_chunks_exact_mut(3)
+        .zip(amounts.par_iter())
+        .enumerate()
+        .for_each(|(i, (out_pixel, &amount))| {
+            let position = amount.clamp(0.0, 1.0) * (BLUR_LEVELS - 1) as f32;
+            let lower = (position.floor() as usize).min(BLUR_LEVELS - 2);
+            let t = position - lower as f32;
+            let (below, above) = (&levels[lower].as_raw()[i * 3..i * 3 + 3], &levels[lower + 1].as_raw()[i * 3..i * 3 + 3]);
+            for channel in 0..3 {
+                out_pixel[channel] = (below[channel] as f32 * (1.0 - t) + above[channel] as f32 * t).round() as u8;
+            }
+        });
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+// Adds back `amount` times the difference from a Gaussian blurred copy. Differences below
+// `threshold` are left alone so flat, noisy areas don't get sharpened.
+pub fn unsharp_mask(img: &DynamicImage, radius: f32, amount: f32, threshold: u8, luminance_only: bool, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+    let mut out_buffer = ImageBuffer::new(width, height);
+
+    send_progress(&progress_tx, 0.0);
+
+    let in_pixels = rgb_img.as_raw();
+    let out_pixels = out_buffer.as_mut();
+    let threshold = threshold as f32;
+
+    if luminance_only {
+        let luma = luma_plane(&rgb_img);
+        let luma_img = GrayImage::from_raw(width, height, luma).unwrap();
+        let blurred = blur(&luma_img, radius);
+
+        send_progress(&progress_tx, 0.5);
+
+        in_pixels
+            .par_chunks_exact(3)
+            .zip(out_pixels.par_chunks_exact_mut(3))
+            .zip(luma_img.as_raw().par_iter().zip(blurred.as_raw().par_iter()))
+            .for_each(|((in_pixel, out_pixel), (&original, &smooth))| {
+                let detail = original as f32 - smooth as f32;
+                let delta = if detail.abs() >= threshold { amount * detail } else { 0.0 };
+                for channel in 0..3 {
+                    out_pixel[channel] = (in_pixel[channel] as f32 + delta).round().clamp(0.0, 255.0) as u8;
+                }
+            });
+    } else {
+        let blurred = blur(&rgb_img, radius);
+
+        send_progress(&progress_tx, 0.5);
+
+        in_pixels
+            .par_chunks_exact(3)
+            .zip(out_pixels.par_chunks_exact_mut(3))
+            .zip(blurred.as_raw().par_chunks_exact(3))
+            .for_each(|((in_pixel, out_pixel), smooth)| {
+                let detail = [
+                    in_pixel[0] as f32 - smooth[0] as f32,
+                    in_pixel[1] as f32 - smooth[1] as f32,
+                    in_pixel[2] as f32 - smooth[2] as f32,
+                ];
+                let strong = detail.iter().any(|difference| difference.abs() >= threshold);
+                for channel in 0..3 {
+                    let delta = if strong { amount * detail[channel] } else { 0.0 };
+                    out_pixel[channel] = (in_pixel[channel] as f32 + delta).round().clamp(0.0, 255.0) as u8;
+                }
+            });
+    }
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+// Edge-preserving blur: neighbours are weighted by spatial distance and by color difference.
+// The fast mode splats into a downsampled bilateral grid (Paris & Durand) keyed on luma, so the
+// cost no longer grows with the spatial sigma.
+pub fn bilateral(img: &DynamicImage, spatial_sigma: f32, range_sigma: f32, fast: bool, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+
+    send_progress(&progress_tx, 0.0);
+
+    let spatial_sigma = spatial_sigma.max(0.1);
+    let range_sigma = range_sigma.max(1.0);
+
+    let result = if fast {
+        bilateral_grid(&rgb_img, spatial_sigma, range_sigma, &progress_tx)
+    } else {
+        bilateral_exact(&rgb_img, spatial_sigma, range_sigma, &progress_tx)
+    };
+
+    send_progress(&progress_tx, 1.0);
+    result
+}
+
+
+
+fn bilateral_exact(rgb_img: &ImageBuffer<Rgb<u8>, Vec<u8>>, spatial_sigma: f32, range_sigma: f32, progress_tx: &Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let (width, height) = rgb_img.dimensions();
+    let mut out_buffer = ImageBuffer::new(width, height);
+
+    let radius = (2.0 * spatial_sigma).ceil() as i32;
+    let side = (2 * radius + 1) as usize;
+    let spatial_weights: Vec<f32> = (0..side * side)
+        .map(|index| {
+            let dx = (index % side) as f32 - radius as f32;
+            let dy = (index / side) as f32 - radius as f32;
+            (-(dx * dx + dy * dy) / (2.0 * spatial_sigma * spatial_sigma)).exp()
+        })
+        .collect();
+
+    // Indexed by the squared RGB distance
+    let range_weights: Vec<f32> = (0..=3 * 255 * 255)
+        .map(|distance| (-(distance as f32) / (2.0 * range_sigma * range_sigma)).exp())
+        .collect();
+
+    let in_pixels = rgb_img.as_raw();
+
+    out_buffer
+        .as_mut()
+        .par_chunks_exact_mut((width * 3) as usize)
+        .enumerate()
+        .for_each(|(y, out_row)| {
+            for x in 0..width as i32 {
+                let center_idx = (y * width as usize + x as usize) * 3;
+                let center = &in_pixels[center_idx..center_idx + 3];
+                let mut sums = [0.0f32; 3];
+                let mut total = 0.0f32;
+
+                for ky in (y as i32 - radius).max(0)..=(y as i32 + radius).min(height as i32 - 1) {
+                    for kx in (x - radius).max(0)..=(x + radius).min(width as i32 - 1) {
+                        let idx = (ky as usize * width as usize + kx as usize) * 3;
+                        let neighbour = &in_pixels[idx..idx + 3];
+                        let distance: i32 = (0..3).map(|channel| (neighbour[channel] as i32 - center[channel] as i32).pow(2)).sum();
+                        let spatial = spatial_weights[(ky - y as i32 + radius) as usize * side + (kx - x + radius) as usize];
+                        let weight = spatial * range_weights[distance as usize];
+
+                        for channel in 0..3 {
+                            sums[channel] += weight * neighbour[channel] as f32;
+                        }
+                        total += weight;
+                    }
+                }
+
+                for channel in 0..3 {
+                    out_row[x as usize * 3 + channel] = (sums[channel] / total).round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        });
+
+    send_progress(progress_tx, 0.5);
+    out_buffer
+}
+
+
+
+// Upper bounds on the grid so small sigmas on large images can't allocate without limit. Past the
+// budget the spatial sampling gets coarser, which smooths a little more than asked.
+const MAX_GRID_DEPTH: f32 = 48.0;
+const GRID_CELL_BUDGET: f32 = (1 << 23) as f32;
+
+fn bilateral_grid(rgb_img: &ImageBuffer<Rgb<u8>, Vec<u8>>, spatial_sigma: f32, range_sigma: f32, progress_tx: &Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let (width, height) = rgb_img.dimensions();
+    let range_sigma = range_sigma.max(255.0 / MAX_GRID_DEPTH);
+    let cells = (width as f32 / spatial_sigma + 3.0) * (height as f32 / spatial_sigma + 3.0) * (255.0 / range_sigma + 3.0);
+    let spatial_sigma = if cells > GRID_CELL_BUDGET { spatial_sigma * (cells / GRID_CELL_BUDGET).sqrt() } else { spatial_sigma };
+    let grid_width = (width as f32 / spatial_sigma).ceil() as usize + 3;
+    let grid_height = (height as f32 / spatial_sigma).ceil() as usize + 3;
+    let grid_depth = (255.0 / range_sigma).ceil() as usize + 3;
+    // Each grid row is one contiguous slab, so splatting and blurring can run per row in parallel
+    let slab_len = grid_depth * grid_width * 4;
+    let cell = |gx: usize, gy: usize, gz: usize| gy * slab_len + (gz * grid_width + gx) * 4;
+
+    // Homogeneous (r, g, b, weight) cells, offset by one so neighbours of edge cells exist
+    let mut grid = vec![0.0f32; grid_width * grid_height * grid_depth * 4];
+    let in_pixels = rgb_img.as_raw();
+    let luma = luma_plane(rgb_img);
+
+    // Image rows only ever land in the grid row they round to, so every slab is filled independently
+    grid.par_chunks_exact_mut(slab_len).enumerate().for_each(|(gy, slab)| {
+        for y in (0..height as usize).filter(|&y| (y as f32 / spatial_sigma).round() as usize + 1 == gy) {
+            for x in 0..width as usize {
+                let idx = y * width as usize + x;
+                let gx = (x as f32 / spatial_sigma).round() as usize + 1;
+                let gz = (luma[idx] as f32 / range_sigma).round() as usize + 1;
+                let target = (gz * grid_width + gx) * 4;
+                for channel in 0..3 {
+                    slab[target + channel] += in_pixels[idx * 3 + channel] as f32;
+                }
+                slab[target + 3] += 1.0;
+            }
+        }
+    });
+
+    // [1 2 1] blur along each grid axis
+    for (step_x, step_y, step_z) in [(1, 0, 0), (0, 1, 0), (0, 0, 1)] {
+        let source = grid.clone();
+        grid.par_chunks_exact_mut(slab_len).enumerate().for_each(|(gy, slab)| {
+            for gz in 0..grid_depth {
+                for gx in 0..grid_width {
+                    let inside = |dx: isize, dy: isize, dz: isize| {
+                        let nx = gx as isize + dx * step_x;
+                        let ny = gy as isize + dy * step_y;
+                        let nz = gz as isize + dz * step_z;
+                        (nx >= 0 && ny >= 0 && nz >= 0 && (nx as usize) < grid_width && (ny as usize) < grid_height && (nz as usize) < grid_depth)
+                            .then(|| cell(nx as usize, ny as usize, nz as usize))
+                    };
+                    let local = (gz * grid_width + gx) * 4;
+                    let center = cell(gx, gy, gz);
+                    for component in 0..4 {
+                        let mut value = 2.0 * source[center + component];
+                        if let Some(previous) = inside(-1, -1, -1) {
+                            value += source[previous + component];
+                        }
+                        if let Some(next) = inside(1, 1, 1) {
+                            value += source[next + component];
+                        }
+                        slab[local + component] = value / 4.0;
+                    }
+                }
+            }
+        });
+    }
+
+    send_progress(progress_tx, 0.5);
+
+    let mut out_buffer = ImageBuffer::new(width, height);
+    out_buffer
+        .as_mut()
+        .par_chunks_exact_mut((width * 3) as usize)
+        .enumerate()
+        .for_each(|(y, out_row)| {
+            let fy = y as f32 / spatial_sigma + 1.0;
+            let y0 = fy.floor() as usize;
+            let ty = fy - y0 as f32;
+
+            for x in 0..width as usize {
+                let fx = x as f32 / spatial_sigma + 1.0;
+                let fz = luma[y * width as usize + x] as f32 / range_sigma + 1.0;
+                let (x0, z0) = (fx.floor() as usize, fz.floor() as usize);
+                let (tx, tz) = (fx - x0 as f32, fz - z0 as f32);
+
+                let mut sample = [0.0f32; 4];
+                for (dz, wz) in [(0, 1.0 - tz), (1, tz)] {
+                    for (dy, wy) in [(0, 1.0 - ty), (1, ty)] {
+                        for (dx, wx) in [(0, 1.0 - tx), (1, tx)] {
+                            let weight = wx * wy * wz;
+                            let source = cell(x0 + dx, y0 + dy, z0 + dz);
+                            for component in 0..4 {
+                                sample[component] += weight * grid[source + component];
+                            }
+                        }
+                    }
+                }
+
+                let idx = (y * width as usize + x) * 3;
+                for channel in 0..3 {
+                    out_row[x * 3 + channel] = if sample[3] > 1e-6 {
+                        (sample[channel] / sample[3]).round().clamp(0.0, 255.0) as u8
+                    } else {
+                        in_pixels[idx + channel]
+                    };
+                }
+            }
+        });
+
+    out_buffer
+}
+
+
+
+// Guided filter (He et al.) with the image's own luma as guide: each output is a local linear
+// function of the guide, so edges in the guide survive while flat areas are averaged.
+// The fast mode fits the linear coefficients on a subsampled image and upsamples them.
+pub fn guided_filter(img: &DynamicImage, radius: u32, epsilon: f32, fast: bool, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+
+    send_progress(&progress_tx, 0.0);
+
+    let subsample = if fast { (radius / 4).max(1) } else { 1 };
+    let guide_pixels: Vec<f32> = rgb_img.as_raw().iter().map(|&value| value as f32 / 255.0).collect();
+    let full = ImageBuffer::<Rgb<f32>, Vec<f32>>::from_raw(width, height, guide_pixels).unwrap();
+
+    let (small_width, small_height) = ((width / subsample).max(1), (height / subsample).max(1));
+    let small = if subsample > 1 { resize(&full, small_width, small_height, FilterType::Triangle) } else { full.clone() };
+    let small_radius = (radius / subsample).max(1);
+
+    // Luma guide packed next to its square so one box pass gives both means
+    let guide: Vec<f32> = small.as_raw()
+        .chunks_exact(3)
+        .map(|pixel| LUMA_WEIGHTS[0] * pixel[0] + LUMA_WEIGHTS[1] * pixel[1] + LUMA_WEIGHTS[2] * pixel[2])
+        .collect();
+    let guide_stats: Vec<f32> = guide.iter().flat_map(|&value| [value, value * value, 0.0]).collect();
+    let products: Vec<f32> = small.as_raw()
+        .chunks_exact(3)
+        .zip(guide.iter())
+        .flat_map(|(pixel, &value)| [pixel[0] * value, pixel[1] * value, pixel[2] * value])
+        .collect();
+
+    let mean_guide = box_mean_rgb(&guide_stats, small_radius, small_width, small_height);
+    let mean_input = box_mean_rgb(small.as_raw(), small_radius, small_width, small_height);
+    let mean_products = box_mean_rgb(&products, small_radius, small_width, small_height);
+
+    send_progress(&progress_tx, 0.5);
+
+    let mut coefficient_a = vec![0.0f32; mean_input.len()];
+    let mut coefficient_b = vec![0.0f32; mean_input.len()];
+    coefficient_a
+        .par_chunks_exact_mut(3)
+        .zip(coefficient_b.par_chunks_exact_mut(3))
+        .enumerate()
+        .for_each(|(index, (a, b))| {
+            let guide_mean = mean_guide[index * 3];
+            let variance = mean_guide[index * 3 + 1] - guide_mean * guide_mean;
+            for channel in 0..3 {
+                let input_mean = mean_input[index * 3 + channel];
+                let covariance = mean_products[index * 3 + channel] - guide_mean * input_mean;
+                a[channel] = covariance / (variance + epsilon);
+                b[channel] = input_mean - a[channel] * guide_mean;
+            }
+        });
+
+    let mut mean_a = box_mean_rgb(&coefficient_a, small_radius, small_width, small_height);
+    let mut mean_b = box_mean_rgb(&coefficient_b, small_radius, small_width, small_height);
+    if subsample > 1 {
+        let upsample = |values: Vec<f32>| {
+            let small_img = ImageBuffer::<Rgb<f32>, Vec<f32>>::from_raw(small_width, small_height, values).unwrap();
+            resize(&small_img, width, height, FilterType::Triangle).into_raw()
+        };
+        mean_a = upsample(mean_a);
+        mean_b = upsample(mean_b);
+    }
+
+    let mut out_buffer = ImageBuffer::new(width, height);
+    out_buffer
+        .as_mut()
+        .par_chunks_exact_mut(3)
+        .zip(full.as_raw().par_chunks_exact(3))
+        .enumerate()
+        .for_each(|(index, (out_pixel, pixel))| {
+            let guide = LUMA_WEIGHTS[0] * pixel[0] + LUMA_WEIGHTS[1] * pixel[1] + LUMA_WEIGHTS[2] * pixel[2];
+            for channel in 0..3 {
+                let value = mean_a[index * 3 + channel] * guide + mean_b[index * 3 + channel];
+                out_pixel[channel] = (value * 255.0).round().clamp(0.0, 255.0) as u8;
+            }
+        });
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+fn box_mean_rgb(pixels: &[f32], radius: u32, width: u32, height: u32) -> Vec<f32> {
+    let horizontal = horizontal_box_blur_linear(pixels, radius, width);
+    let transposed = transpose_rgb(&horizontal, width, height);
+    let vertical = horizontal_box_blur_linear(&transposed, radius, height);
+    transpose_rgb(&vertical, height, width)
+}
+
+
+
+// Per-channel median using column histograms (Perreault & Hebert): each row step updates one
+// histogram per column and each pixel step adds one column and drops another, so the cost per
+// pixel doesn't depend on the radius. Bands of rows are processed in parallel.
+pub fn median(img: &DynamicImage, radius: u32, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    const BAND_HEIGHT: usize = 64;
+
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+    let (width, height) = (width as usize, height as usize);
+    let mut out_buffer = ImageBuffer::new(width as u32, height as u32);
+
+    send_progress(&progress_tx, 0.0);
+
+    let radius = radius as isize;
+    let in_pixels = rgb_img.as_raw();
+    let clamp_x = |x: isize| x.clamp(0, width as isize - 1) as usize;
+    let clamp_y = |y: isize| y.clamp(0, height as isize - 1) as usize;
+    let half = ((2 * radius + 1) * (2 * radius + 1) / 2) as u32;
+
+    out_buffer
+        .as_mut()
+        .par_chunks_mut(width * 3 * BAND_HEIGHT)
+        .enumerate()
+        .for_each(|(band, band_rows)| {
+            let top = band * BAND_HEIGHT;
+            let mut columns = vec![[[0u32; 256]; 3]; width];
+
+            for (x, column) in columns.iter_mut().enumerate() {
+                for ky in top as isize - radius..=top as isize + radius {
+                    let idx = (clamp_y(ky) * width + x) * 3;
+                    for channel in 0..3 {
+                        column[channel][in_pixels[idx + channel] as usize] += 1;
+                    }
+                }
+            }
+
+            for (row, out_row) in band_rows.chunks_exact_mut(width * 3).enumerate() {
+                let y = top + row;
+                if row > 0 {
+                    let leaving = clamp_y(y as isize - radius - 1);
+                    let entering = clamp_y(y as isize + radius);
+                    for (x, column) in columns.iter_mut().enumerate() {
+                        for channel in 0..3 {
+                            column[channel][in_pixels[(leaving * width + x) * 3 + channel] as usize] -= 1;
+                            column[channel][in_pixels[(entering * width + x) * 3 + channel] as usize] += 1;
+                        }
+                    }
+                }
+
+                let mut kernel = [[0u32; 256]; 3];
+                for kx in -radius..=radius {
+                    for channel in 0..3 {
+                        for value in 0..256 {
+                            kernel[channel][value] += columns[clamp_x(kx)][channel][value];
+                        }
+                    }
+                }
+
+                for x in 0..width {
+                    if x > 0 {
+                        let leaving = clamp_x(x as isize - radius - 1);
+                        let entering = clamp_x(x as isize + radius);
+                        for channel in 0..3 {
+                            for value in 0..256 {
+                                kernel[channel][value] = kernel[channel][value] + columns[entering][channel][value] - columns[leaving][channel][value];
+                            }
+                        }
+                    }
+
+                    for channel in 0..3 {
+                        let mut count = 0;
+                        for (value, &bucket) in kernel[channel].iter().enumerate() {
+                            count += bucket;
+                            if count > half {
+                                out_row[x * 3 + channel] = value as u8;
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+// Non-local means: every pixel becomes a weighted mean of the pixels in its search window,
+// weighted by how similar their surrounding patches are. Patch distances are computed one
+// offset at a time with box means, so the patch size doesn't affect the running time.
+pub fn nl_means(img: &DynamicImage, strength: f32, patch_radius: u32, search_radius: u32, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+
+    send_progress(&progress_tx, 0.0);
+
+    let pixels: Vec<f32> = rgb_img.as_raw().iter().map(|&value| value as f32).collect();
+    let (w, h) = (width as isize, height as isize);
+    let filtering = (strength * strength).max(1e-3);
+    let search = search_radius as isize;
+
+    let mut sums = vec![0.0f32; pixels.len()];
+    let mut totals = vec![0.0f32; pixels.len() / 3];
+    let offsets: Vec<(isize, isize)> = (-search..=search).flat_map(|dy| (-search..=search).map(move |dx| (dx, dy))).collect();
+
+    // Three offsets share one box pass, one per channel of the distance buffer
+    for (done, group) in offsets.chunks(3).enumerate() {
+        let shifted_index = |index: usize, (dx, dy): (isize, isize)| {
+            let x = (index as isize % w + dx).clamp(0, w - 1);
+            let y = (index as isize / w + dy).clamp(0, h - 1);
+            (y * w + x) as usize * 3
+        };
+
+        let mut differences = vec![0.0f32; pixels.len()];
+        differences.par_chunks_exact_mut(3).enumerate().for_each(|(index, difference)| {
+            for (slot, &offset) in group.iter().enumerate() {
+                let shifted = shifted_index(index, offset);
+                difference[slot] = (0..3).map(|channel| (pixels[index * 3 + channel] - pixels[shifted + channel]).powi(2)).sum::<f32>() / 3.0;
+            }
+        });
+        let distances = box_mean_rgb(&differences, patch_radius, width, height);
+
+        sums.par_chunks_exact_mut(3)
+            .zip(totals.par_iter_mut())
+            .enumerate()
+            .for_each(|(index, (sum, total))| {
+                for (slot, &offset) in group.iter().enumerate() {
+                    let weight = (-distances[index * 3 + slot] / filtering).exp();
+                    let shifted = shifted_index(index, offset);
+                    for channel in 0..3 {
+                        sum[channel] += weight * pixels[shifted + channel];
+                    }
+                    *total += weight;
+                }
+            });
+
+        send_progress(&progress_tx, (3 * done + group.len()) as f64 / offsets.len() as f64 * 0.99);
+    }
+
+    let mut out_buffer = ImageBuffer::new(width, height);
+    out_buffer
+        .as_mut()
+        .par_chunks_exact_mut(3)
+        .zip(sums.par_chunks_exact(3).zip(totals.par_iter()))
+        .for_each(|(out_pixel, (sum, &total))| {
+            for channel in 0..3 {
+                out_pixel[channel] = (sum[channel] / total).round().clamp(0.0, 255.0) as u8;
+            }
+        });
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+// Grayscale morphology per channel, so binary masks from thresholding work as well as photos.
+pub fn morphology(img: &DynamicImage, operation: MorphOperation, element: StructuringElement, radius: u32, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+
+    send_progress(&progress_tx, 0.0);
+
+    let pixels = rgb_img.as_raw();
+    let erode = |input: &[u8]| morph_pass(input, width, height, radius, element, false);
+    let dilate = |input: &[u8]| morph_pass(input, width, height, radius, element, true);
+    let difference = |a: &[u8], b: &[u8]| -> Vec<u8> {
+        a.par_iter().zip(b.par_iter()).map(|(&a, &b)| a.saturating_sub(b)).collect()
+    };
+
+    let result = match operation {
+        MorphOperation::Erode => erode(pixels),
+        MorphOperation::Dilate => dilate(pixels),
+        MorphOperation::Open => dilate(&erode(pixels)),
+        MorphOperation::Close => erode(&dilate(pixels)),
+        MorphOperation::TopHat => difference(pixels, &dilate(&erode(pixels))),
+        MorphOperation::BlackHat => difference(&erode(&dilate(pixels)), pixels),
+        MorphOperation::Gradient => difference(&dilate(pixels), &erode(pixels)),
+    };
+
+    send_progress(&progress_tx, 1.0);
+    ImageBuffer::from_vec(width, height, result).unwrap()
+}
+
+
+
+// One erosion (min) or dilation (max) over the structuring element. Squares are separable and a
+// cross is the union of a horizontal and a vertical line. Small disks are the exact union of
+// horizontal chords; larger ones use a regular octagon, the sum of a square and two diagonal
+// lines, so the cost stays the same for any radius.
+fn morph_pass(pixels: &[u8], width: u32, height: u32, radius: u32, element: StructuringElement, take_max: bool) -> Vec<u8> {
+    let vertical = |input: &[u8], radius: u32| {
+        let transposed = transpose_rgb(input, width, height);
+        transpose_rgb(&line_extreme_rows(&transposed, height, radius, take_max), height, width)
+    };
+    let pick = |a: u8, b: u8| if take_max { a.max(b) } else { a.min(b) };
+
+    match element {
+        StructuringElement::Square => vertical(&line_extreme_rows(pixels, width, radius, take_max), radius),
+        StructuringElement::Cross => {
+            let horizontal = line_extreme_rows(pixels, width, radius, take_max);
+            let vertical = vertical(pixels, radius);
+            horizontal.par_iter().zip(vertical.par_iter()).map(|(&a, &b)| pick(a, b)).collect()
+        }
+        StructuringElement::Disk if radius > EXACT_DISK_RADIUS => {
+            // A square of half-side a plus diagonals of half-length b reaches a + 2b along the axes
+            // and a + b along the diagonals; b = r(1 - 1/sqrt(2)) makes both match the radius
+            let diagonal = (radius as f32 * (1.0 - std::f32::consts::FRAC_1_SQRT_2)).round() as u32;
+            let side = radius - 2 * diagonal;
+            let square = vertical(&line_extreme_rows(pixels, width, side, take_max), side);
+            let falling = diagonal_extreme(&square, width, height, diagonal, false, take_max);
+            diagonal_extreme(&falling, width, height, diagonal, true, take_max)
+        }
+        StructuringElement::Disk => {
+            let radius = radius as i64;
+            let chord = |dy: i64| ((radius * radius - dy * dy) as f64).sqrt().floor() as u32;
+            let chords: HashMap<u32, Vec<u8>> = (0..=radius)
+                .map(chord)
+                .collect::<HashSet<u32>>()
+                .into_iter()
+                .map(|half_width| (half_width, line_extreme_rows(pixels, width, half_width, take_max)))
+                .collect();
+
+            let stride = (width * 3) as usize;
+            let mut result = vec![0u8; pixels.len()];
+            result.par_chunks_exact_mut(stride).enumerate().for_each(|(y, out_row)| {
+                out_row.copy_from_slice(&chords[&chord(0)][y * stride..(y + 1) * stride]);
+                for dy in 1..=radius {
+                    let rows = &chords[&chord(dy)];
+                    for source_y in [y as i64 - dy, y as i64 + dy] {
+                        if source_y < 0 || source_y >= height as i64 {
+                            continue;
+                        }
+                        let source = &rows[source_y as usize * stride..(source_y as usize + 1) * stride];
+                        for (out_value, &value) in out_row.iter_mut().zip(source) {
+                            *out_value = pick(*out_value, value);
+                        }
+                    }
+                }
+            });
+            result
+        }
+    }
+}
+
+const EXACT_DISK_RADIUS: u32 = 4;
+
+
+
+// Running min/max along 45 degree lines. Shifting every row by its index turns the diagonals into
+// columns of a wider sheared image; the gaps are filled with a value that never wins.
+fn diagonal_extreme(pixels: &[u8], width: u32, height: u32, radius: u32, rising: bool, take_max: bool) -> Vec<u8> {
+    let (width, height) = (width as usize, height as usize);
+    let sheared_width = width + height - 1;
+    let offset = |y: usize| if rising { y } else { height - 1 - y };
+    let neutral = if take_max { 0 } else { 255 };
+
+    let mut sheared = vec![neutral; sheared_width * height * 3];
+    sheared.par_chunks_exact_mut(sheared_width * 3).enumerate().for_each(|(y, row)| {
+        let start = offset(y) * 3;
+        row[start..start + width * 3].copy_from_slice(&pixels[y * width * 3..(y + 1) * width * 3]);
+    });
+
+    let transposed = transpose_rgb(&sheared, sheared_width as u32, height as u32);
+    let filtered = transpose_rgb(&line_extreme_rows(&transposed, height as u32, radius, take_max), height as u32, sheared_width as u32);
+
+    let mut result = vec![0u8; pixels.len()];
+    result.par_chunks_exact_mut(width * 3).enumerate().for_each(|(y, row)| {
+        let start = (y * sheared_width + offset(y)) * 3;
+        row.copy_from_slice(&filtered[start..start + width * 3]);
+    });
+    result
+}
+
+
+
+// Running min/max over a window of 2 * radius + 1 along each row using van Herk/Gil-Werman:
+// block-wise prefix and suffix extremes give every window with one comparison, whatever the radius.
+// Pixels past the border are ignored.
+fn line_extreme_rows(pixels: &[u8], width: u32, radius: u32, take_max: bool) -> Vec<u8> {
+    let width = width as usize;
+    let radius = radius as usize;
+    let window = 2 * radius + 1;
+    let padded_len = (width + 2 * radius).div_ceil(window) * window;
+    let identity = if take_max { 0u8 } else { 255u8 };
+    let pick = |a: u8, b: u8| if take_max { a.max(b) } else { a.min(b) };
+
+    let mut result = vec![0u8; pixels.len()];
+    result.par_chunks_exact_mut(width * 3).enumerate().for_each(|(y, out_row)| {
+        let in_row = &pixels[y * width * 3..(y + 1) * width * 3];
+        let mut padded = vec![identity; padded_len];
+        let mut prefix = vec![identity; padded_len];
+        let mut suffix = vec![identity; padded_len];
+
+        for channel in 0..3 {
+            for x in 0..width {
+                padded[x + radius] = in_row[x * 3 + channel];
+            }
+
+            for i in 0..padded_len {
+                prefix[i] = if i % window == 0 { padded[i] } else { pick(prefix[i - 1], padded[i]) };
+            }
+            for i in (0..padded_len).rev() {
+                suffix[i] = if i % window == window - 1 { padded[i] } else { pick(suffix[i + 1], padded[i]) };
+            }
+
+            // Window padded[x..x + window] is the original row from x - radius to x + radius
+            for x in 0..width {
+                out_row[x * 3 + channel] = pick(suffix[x], prefix[x + window - 1]);
+            }
+        }
+    });
+
+    result
+}
+
+
+
+// Applies the kernel as written (correlation, centered on the pixel), divides by `divisor` and adds
+// `bias`. A zero divisor uses the kernel sum, or 1 when the kernel sums to zero. Rank-1 kernels run
+// as a horizontal and a vertical pass.
+pub fn convolve(img: &DynamicImage, kernel: &Kernel, divisor: f32, bias: f32, border: BorderMode, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+
+    send_progress(&progress_tx, 0.0);
+
+    let sum: f32 = kernel.values.iter().sum();
+    let divisor = if divisor != 0.0 { divisor } else if sum.abs() > 1e-6 { sum } else { 1.0 };
+    let pixels: Vec<f32> = rgb_img.as_raw().iter().map(|&value| value as f32).collect();
+    let constant = match border {
+        BorderMode::Constant(color) => color.0.map(|channel| channel as f32),
+        _ => [0.0; 3],
+    };
+
+    let filtered = match kernel.separable() {
+        Some((column, row)) => {
+            let horizontal = convolve_pass(&pixels, width, height, std::slice::from_ref(&row), border, constant);
+
+            send_progress(&progress_tx, 0.5);
+
+            // Out-of-image rows of the horizontal result hold the constant color times the row sum
+            let row_sum: f32 = row.iter().sum();
+            let columns: Vec<Vec<f32>> = column.iter().map(|&value| vec![value]).collect();
+            convolve_pass(&horizontal, width, height, &columns, border, constant.map(|channel| channel * row_sum))
+        }
+        None => {
+            let rows: Vec<Vec<f32>> = kernel.values.chunks_exact(kernel.width).map(|row| row.to_vec()).collect();
+            convolve_pass(&pixels, width, height, &rows, border, constant)
+        }
+    };
+
+    let mut out_buffer = ImageBuffer::new(width, height);
+    out_buffer
+        .as_mut()
+        .par_iter_mut()
+        .zip(filtered.par_iter())
+        .for_each(|(out_value, &value)| {
+            *out_value = (value / divisor + bias).round().clamp(0.0, 255.0) as u8;
+        });
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+// `constant` is the value read outside the image when the border mode is constant.
+fn convolve_pass(pixels: &[f32], width: u32, height: u32, rows: &[Vec<f32>], border: BorderMode, constant: [f32; 3]) -> Vec<f32> {
+    let (width, height) = (width as usize, height as usize);
+    let kernel_height = rows.len() as isize;
+    let kernel_width = rows[0].len() as isize;
+
+    let mut result = vec![0.0f32; pixels.len()];
+    result.par_chunks_exact_mut(width * 3).enumerate().for_each(|(y, out_row)| {
+        for x in 0..width {
+            let mut sums = [0.0f32; 3];
+            for (ky, kernel_row) in rows.iter().enumerate() {
+                let source_y = border.resolve(y as isize + ky as isize - kernel_height / 2, height);
+                for (kx, &weight) in kernel_row.iter().enumerate() {
+                    if weight == 0.0 {
+                        continue;
+                    }
+                    let source_x = border.resolve(x as isize + kx as isize - kernel_width / 2, width);
+                    match (source_x, source_y) {
+                        (Some(source_x), Some(source_y)) => {
+                            let idx = (source_y * width + source_x) * 3;
+                            for channel in 0..3 {
+                                sums[channel] += weight * pixels[idx + channel];
+                            }
+                        }
+                        _ => {
+                            for channel in 0..3 {
+                                sums[channel] += weight * constant[channel];
+                            }
+                        }
+                    }
+                }
+            }
+            out_row[x * 3..x * 3 + 3].copy_from_slice(&sums);
+        }
+    });
+
+    result
+}
+
+
+
+pub fn sharpen(img: &DynamicImage, strenght: f32, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    
+    let rgb_img = img.to_rgb8();
+
+    let (width, height) = rgb_img.dimensions();
+    let mut out_buffer = ImageBuffer::new(width, height);
+
+    send_progress(&progress_tx, 0.0);
+
+    let in_pixels = rgb_img.as_raw();
+    
+    out_buffer.as_mut().par_chunks_exact_mut((width * 3) as usize)
+        .enumerate()
+        .for_each(|(y, out_row)| {
+            if y == 0 || y == height as usize - 1 {
+                let src_start = (y * width as usize * 3) as usize;
+                out_row.copy_from_slice(&in_pixels[src_start..src_start + (width * 3) as usize]);
+                return;
+            }
+            
+            let row_stride = (width * 3) as usize;
+            
+            for x in 0..width {
+                if x == 0 || x == width - 1 {
+                
+        
+                    let dst_idx = (x * 3) as usize;
+                    out_row[dst_idx] = in_pixels[(y * width as usize + x as usize) * 3];
+                    out_row[dst_idx + 1] = in_pixels[(y * width as usize + x as usize) * 3 + 1];
+                    out_row[dst_idx + 2] = in_pixels[(y * width as usize + x as usize) * 3 + 2];
+                    continue;
+                }
+                
+                let center_idx = ((y * width as usize + x as usize) * 3) as usize;
+
+                let center_red = in_pixels[center_idx] as f32;
+                let center_green = in_pixels[center_idx + 1] as f32;
+                let center_blue = in_pixels[center_idx + 2] as f32;
+
+                let top_red = in_pixels[center_idx - row_stride] as f32;
+                let top_green = in_pixels[center_idx - row_stride + 1] as f32;
+                let top_blue = in_pixels[center_idx - row_stride + 2] as f32;
+
+                let left_red = in_pixels[center_idx - 3] as f32;
+                let left_green = in_pixels[center_idx - 2] as f32;
+                let left_blue = in_pixels[center_idx - 1] as f32;
+
+                let right_red = in_pixels[center_idx + 3] as f32;
+                let right_green = in_pixels[center_idx + 4] as f32;
+                let right_blue = in_pixels[center_idx + 5] as f32;
+
+                let bottom_red = in_pixels[center_idx + row_stride] as f32;
+                let bottom_green = in_pixels[center_idx + row_stride + 1] as f32;
+                let bottom_blue = in_pixels[center_idx + row_stride + 2] as f32;
+
+                let sharpened_red = center_red * 5.0 - (top_red + left_red + right_red + bottom_red);
+                let sharpened_green = center_green * 5.0 - (top_green + left_green + right_green + bottom_green);
+                let sharpened_blue = center_blue * 5.0 - (top_blue + left_blue + right_blue + bottom_blue);
+
+                let final_red = center_red + strenght * (sharpened_red - center_red);
+                let final_green = center_green + strenght * (sharpened_green - center_green);
+                let final_blue = center_blue + strenght * (sharpened_blue - center_blue);
+
+                let out_idx = (x * 3) as usize;
+                out_row[out_idx] = final_red.clamp(0.0, 255.0) as u8;
+                out_row[out_idx + 1] = final_green.clamp(0.0, 255.0) as u8;
+                out_row[out_idx + 2] = final_blue.clamp(0.0, 255.0) as u8;
+            }
+        });
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+pub fn edge_detection(img: &DynamicImage, operator: EdgeOperator, output: EdgeOutput, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+    let mut out_buffer = ImageBuffer::new(width, height);
+
+    send_progress(&progress_tx, 0.0);
+
+    let luma: Vec<f32> = rgb_img.as_raw().par_chunks_exact(3).map(luma).collect();
+    let smoothed = match operator {
+        EdgeOperator::LaplacianOfGaussian { sigma, .. } | EdgeOperator::Canny { sigma, .. } => {
+            // Float images are blurred in the 0 to 1 range
+            let normalized: Vec<f32> = luma.iter().map(|value| value / 255.0).collect();
+            let luma_img = ImageBuffer::<Luma<f32>, Vec<f32>>::from_raw(width, height, normalized).unwrap();
+            blur(&luma_img, sigma.max(0.1)).into_raw().into_iter().map(|value| value * 255.0).collect()
+        }
+        _ => luma,
+    };
+
+    let kernel = match operator {
+        EdgeOperator::Scharr => GradientKernel::SCHARR,
+        EdgeOperator::Prewitt => GradientKernel::PREWITT,
+        _ => GradientKernel::SOBEL,
+    };
+    let (gx, gy) = gradients(&smoothed, width, height, kernel);
+
+    send_progress(&progress_tx, 0.5);
+
+    let strength: Vec<f32> = match operator {
+        EdgeOperator::Sobel | EdgeOperator::Scharr | EdgeOperator::Prewitt => gx
+            .par_iter()
+            .zip(gy.par_iter())
+            .map(|(x, y)| (x * x + y * y).sqrt())
+            .collect(),
+        EdgeOperator::LaplacianOfGaussian { sigma, threshold } => laplacian_zero_crossings(&smoothed, width, height, sigma, threshold),
+        EdgeOperator::Canny { low, high, .. } => canny_edges(&gx, &gy, width, height, low, high),
+    };
+
+    let in_pixels = rgb_img.as_raw();
+    out_buffer
+        .as_mut()
+        .par_chunks_exact_mut(3)
+        .enumerate()
+        .for_each(|(index, out_pixel)| {
+            let edge = strength[index].clamp(0.0, 255.0);
+            match output {
+                EdgeOutput::Magnitude => out_pixel.fill(edge as u8),
+                EdgeOutput::Direction => {
+                    out_pixel.copy_from_slice(&direction_color(gy[index].atan2(gx[index]), edge / 255.0));
+                }
+                EdgeOutput::Overlay(color) => {
+                    let alpha = edge / 255.0;
+                    for channel in 0..3 {
+                        let original = in_pixels[index * 3 + channel] as f32;
+                        out_pixel[channel] = (original + (color[channel] as f32 - original) * alpha).round() as u8;
+                    }
+                }
+            }
+        });
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+// 3x3 derivative kernels as smoothing weights across the derivative, scaled to Sobel's gain.
+#[derive(Clone, Copy)]
+pub(crate) struct GradientKernel {
+    side: f32,
+    center: f32,
+    scale: f32,
+}
+
+impl GradientKernel {
+    pub(crate) const SOBEL: GradientKernel = GradientKernel { side: 1.0, center: 2.0, scale: 1.0 };
+    const SCHARR: GradientKernel = GradientKernel { side: 3.0, center: 10.0, scale: 0.25 };
+    const PREWITT: GradientKernel = GradientKernel { side: 1.0, center: 1.0, scale: 4.0 / 3.0 };
+}
+
+
+
+// Horizontal and vertical derivatives with edge pixels replicated past the border.
+pub(crate) fn gradients(plane: &[f32], width: u32, height: u32, kernel: GradientKernel) -> (Vec<f32>, Vec<f32>) {
+    let (width, height) = (width as usize, height as usize);
+    let at = |x: isize, y: isize| plane[y.clamp(0, height as isize - 1) as usize * width + x.clamp(0, width as isize - 1) as usize];
+
+    let mut gx = vec![0.0f32; plane.len()];
+    let mut gy = vec![0.0f32; plane.len()];
+
+    gx.par_chunks_exact_mut(width)
+        .zip(gy.par_chunks_exact_mut(width))
+        .enumerate()
+        .for_each(|(y, (gx_row, gy_row))| {
+            let y = y as isize;
+            for x in 0..width {
+                let x = x as isize;
+                let horizontal = kernel.side * (at(x + 1, y - 1) - at(x - 1, y - 1))
+                    + kernel.center * (at(x + 1, y) - at(x - 1, y))
+                    + kernel.side * (at(x + 1, y + 1) - at(x - 1, y + 1));
+                let vertical = kernel.side * (at(x - 1, y + 1) - at(x - 1, y - 1))
+                    + kernel.center * (at(x, y + 1) - at(x, y - 1))
+                    + kernel.side * (at(x + 1, y + 1) - at(x + 1, y - 1));
+
+                gx_row[x as usize] = horizontal * kernel.scale;
+                gy_row[x as usize] = vertical * kernel.scale;
+            }
+        });
+
+    (gx, gy)
+}
+
+
+
+// Marks pixels where the Laplacian of the smoothed image changes sign with a slope above `threshold`.
+// The Laplacian is scale-normalized by sigma squared and brought to Sobel's gain so thresholds compare.
+fn laplacian_zero_crossings(plane: &[f32], width: u32, height: u32, sigma: f32, threshold: f32) -> Vec<f32> {
+    let (width, height) = (width as usize, height as usize);
+    let at = |x: isize, y: isize| plane[y.clamp(0, height as isize - 1) as usize * width + x.clamp(0, width as isize - 1) as usize];
+    let gain = 4.0 * sigma * sigma;
+    let laplacian: Vec<f32> = (0..plane.len())
+        .into_par_iter()
+        .map(|index| {
+            let (x, y) = ((index % width) as isize, (index / width) as isize);
+            gain * (at(x - 1, y) + at(x + 1, y) + at(x, y - 1) + at(x, y + 1) - 4.0 * at(x, y))
+        })
+        .collect();
+
+    (0..plane.len())
+        .into_par_iter()
+        .map(|index| {
+            let (x, y) = (index % width, index / width);
+            let value = laplacian[index];
+            let crossing = [(1, 0), (0, 1)].iter().any(|&(dx, dy)| {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx >= width || ny >= height {
+                    return false;
+                }
+                let neighbour = laplacian[ny * width + nx];
+                value.signum() != neighbour.signum() && (value - neighbour).abs() >= threshold
+            });
+            if crossing { 255.0 } else { 0.0 }
+        })
+        .collect()
+}
+
+
+
+// Non-maximum suppression along the quantized gradient direction, then hysteresis: weak edges
+// survive only when connected to a strong one.
+fn canny_edges(gx: &[f32], gy: &[f32], width: u32, height: u32, low: f32, high: f32) -> Vec<f32> {
+    let (width, height) = (width as usize, height as usize);
+    let magnitude: Vec<f32> = gx.par_iter().zip(gy.par_iter()).map(|(x, y)| (x * x + y * y).sqrt()).collect();
+    let at = |x: isize, y: isize| {
+        if x < 0 || y < 0 || x >= width as isize || y >= height as isize { 0.0 } else { magnitude[y as usize * width + x as usize] }
+    };
+
+    let thinned: Vec<f32> = (0..magnitude.len())
+        .into_par_iter()
+        .map(|index| {
+            let value = magnitude[index];
+            if value < low {
+                return 0.0;
+            }
+            let (x, y) = ((index % width) as isize, (index / width) as isize);
+            let angle = gy[index].atan2(gx[index]).to_degrees().rem_euclid(180.0);
+            let (dx, dy) = if !(22.5..157.5).contains(&angle) {
+                (1, 0)
+            } else if angle < 67.5 {
+                (1, 1)
+            } else if angle < 112.5 {
+                (0, 1)
+            } else {
+                (-1, 1)
+            };
+            if value > at(x + dx, y + dy) && value >= at(x - dx, y - dy) { value } else { 0.0 }
+        })
+        .collect();
+
+    let mut edges = vec![0.0f32; thinned.len()];
+    let mut stack: Vec<usize> = (0..thinned.len()).filter(|&index| thinned[index] >= high).collect();
+    for &index in &stack {
+        edges[index] = 255.0;
+    }
+
+    while let Some(index) = stack.pop() {
+        let (x, y) = ((index % width) as isize, (index / width) as isize);
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                    continue;
+                }
+                let neighbour = ny as usize * width + nx as usize;
+                if edges[neighbour] == 0.0 && thinned[neighbour] >= low {
+                    edges[neighbour] = 255.0;
+                    stack.push(neighbour);
+                }
+            }
+        }
+    }
+
+    edges
+}
+
+
+
+// Hue encodes the gradient angle, brightness the edge strength.
+fn direction_color(angle: f32, strength: f32) -> [u8; 3] {
+    let hue = angle.to_degrees().rem_euclid(360.0) / 60.0;
+    let fraction = hue - hue.floor();
+    let (rising, falling) = (fraction, 1.0 - fraction);
+    let rgb = match hue as u32 {
+        0 => [1.0, rising, 0.0],
+        1 => [falling, 1.0, 0.0],
+        2 => [0.0, 1.0, rising],
+        3 => [0.0, falling, 1.0],
+        4 => [rising, 0.0, 1.0],
+        _ => [1.0, 0.0, falling],
+    };
+    rgb.map(|channel| (channel * strength * 255.0).round() as u8)
+}
+
+
+
+pub fn thresholding(img: &DynamicImage, threshold: u8, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+
+    let mut out_buffer = ImageBuffer::new(width, height);
+
+    send_progress(&progress_tx, 0.0);
+
+    let in_pixels = rgb_img.as_raw();
+    let out_pixels = out_buffer.as_mut();
+
+    in_pixels
+        .par_chunks_exact(3)
+        .zip(out_pixels.par_chunks_exact_mut(3))
         .for_each(|(in_pixel, out_pixel)| {
-            let gray = (0.299 * in_pixel[0] as f32 + 
-                            0.587 * in_pixel[1] as f32 + 
-                            0.114 * in_pixel[2] as f32) as u8;
+            let gray = luma(in_pixel) as u8;
 
             let binary_value = if gray > threshold {255} else {0};
 
@@ -389,4 +2002,376 @@ pub fn thresholding(img: &DynamicImage, threshold: u8, progress_tx: Option<Progr
 
     send_progress(&progress_tx, 1.0);
     out_buffer
-}
\ No newline at end of file
+}
+
+
+// Otsu picks one global threshold; the local methods compare each pixel against statistics of its
+// window, minus `offset`. Sauvola scales the local mean by the local contrast with factor `k`.
+pub fn adaptive_threshold(img: &DynamicImage, method: ThresholdMethod, window: u32, offset: f32, k: f32, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+
+    send_progress(&progress_tx, 0.0);
+
+    let luma = luma_plane(&rgb_img);
+    let radius = window / 2;
+
+    let thresholds: Vec<f32> = match method {
+        ThresholdMethod::Otsu => vec![otsu_threshold(&luma) as f32; luma.len()],
+        ThresholdMethod::Mean => {
+            let stats: Vec<f32> = luma.iter().flat_map(|&value| [value as f32, 0.0, 0.0]).collect();
+            box_mean_rgb(&stats, radius, width, height).chunks_exact(3).map(|mean| mean[0] - offset).collect()
+        }
+        ThresholdMethod::Gaussian => {
+            // Same window to sigma mapping as OpenCV's getGaussianKernel
+            let sigma = 0.3 * ((window.max(3) as f32 - 1.0) * 0.5 - 1.0) + 0.8;
+            let normalized: Vec<f32> = luma.iter().map(|&value| value as f32 / 255.0).collect();
+            let luma_img = ImageBuffer::<Luma<f32>, Vec<f32>>::from_raw(width, height, normalized).unwrap();
+            blur(&luma_img, sigma).into_raw().iter().map(|value| value * 255.0 - offset).collect()
+        }
+        ThresholdMethod::Sauvola => {
+            const DYNAMIC_RANGE: f32 = 128.0;
+            let stats: Vec<f32> = luma
+                .iter()
+                .flat_map(|&value| {
+                    let value = value as f32 / 255.0;
+                    [value, value * value, 0.0]
+                })
+                .collect();
+            box_mean_rgb(&stats, radius, width, height)
+                .chunks_exact(3)
+                .map(|moments| {
+                    let mean = moments[0] * 255.0;
+                    let deviation = (moments[1] - moments[0] * moments[0]).max(0.0).sqrt() * 255.0;
+                    mean * (1.0 + k * (deviation / DYNAMIC_RANGE - 1.0)) - offset
+                })
+                .collect()
+        }
+    };
+
+    send_progress(&progress_tx, 0.5);
+
+    let mut out_buffer = ImageBuffer::new(width, height);
+    out_buffer
+        .as_mut()
+        .par_chunks_exact_mut(3)
+        .zip(luma.par_iter().zip(thresholds.par_iter()))
+        .for_each(|(out_pixel, (&value, &threshold))| {
+            out_pixel.fill(if value as f32 > threshold { 255 } else { 0 });
+        });
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+// Threshold that maximizes the between-class variance of the luma histogram.
+fn otsu_threshold(luma: &[u8]) -> u8 {
+    let mut histogram = [0u64; 256];
+    for &value in luma {
+        histogram[value as usize] += 1;
+    }
+
+    let total = luma.len() as f64;
+    let weighted_total: f64 = histogram.iter().enumerate().map(|(value, &count)| value as f64 * count as f64).sum();
+
+    let mut background = 0.0f64;
+    let mut background_sum = 0.0f64;
+    let mut best = (0u8, -1.0f64);
+
+    for (value, &count) in histogram.iter().enumerate() {
+        background += count as f64;
+        background_sum += value as f64 * count as f64;
+        let foreground = total - background;
+        if background == 0.0 || foreground == 0.0 {
+            continue;
+        }
+
+        let background_mean = background_sum / background;
+        let foreground_mean = (weighted_total - background_sum) / foreground;
+        let between = background * foreground * (background_mean - foreground_mean).powi(2);
+        if between > best.1 {
+            best = (value as u8, between);
+        }
+    }
+
+    best.0
+}
+
+
+
+pub fn histogram_equalization(img: &DynamicImage, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+
+    send_progress(&progress_tx, 0.0);
+
+    let luma = luma_plane(&rgb_img);
+    let histogram = luma
+        .par_chunks(4096)
+        .fold(|| [0u32; 256], |mut histogram, chunk| {
+            for &value in chunk {
+                histogram[value as usize] += 1;
+            }
+            histogram
+        })
+        .reduce(|| [0u32; 256], |mut a, b| {
+            for (total, count) in a.iter_mut().zip(b.iter()) {
+                *total += count;
+            }
+            a
+        });
+
+    let mut cdf = [0u32; 256];
+    let mut running = 0u32;
+    for (value, count) in histogram.iter().enumerate() {
+        running += count;
+        cdf[value] = running;
+    }
+
+    let total = luma.len() as u32;
+    let cdf_min = cdf.iter().copied().find(|&c| c > 0).unwrap_or(0);
+    let mut lut = [0u8; 256];
+    if total > cdf_min {
+        for (value, entry) in lut.iter_mut().enumerate() {
+            let scaled = cdf[value].saturating_sub(cdf_min) as f32 / (total - cdf_min) as f32;
+            *entry = (scaled * 255.0).round() as u8;
+        }
+    } else {
+        for (value, entry) in lut.iter_mut().enumerate() {
+            *entry = value as u8;
+        }
+    }
+
+    send_progress(&progress_tx, 0.5);
+
+    let new_luma: Vec<u8> = luma.par_iter().map(|&value| lut[value as usize]).collect();
+    let out_buffer = replace_luma(&rgb_img, &luma, &new_luma);
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+pub fn clahe(img: &DynamicImage, grid_size: u32, clip_limit: f32, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+
+    send_progress(&progress_tx, 0.0);
+
+    let luma = luma_plane(&rgb_img);
+    let tiles_x = grid_size.clamp(1, width.max(1));
+    let tiles_y = grid_size.clamp(1, height.max(1));
+
+    let tile_luts: Vec<[u8; 256]> = (0..tiles_x * tiles_y)
+        .into_par_iter()
+        .map(|tile| {
+            let tile_x = tile % tiles_x;
+            let tile_y = tile / tiles_x;
+            let x0 = tile_x * width / tiles_x;
+            let x1 = (tile_x + 1) * width / tiles_x;
+            let y0 = tile_y * height / tiles_y;
+            let y1 = (tile_y + 1) * height / tiles_y;
+
+            let mut histogram = [0u32; 256];
+            for y in y0..y1 {
+                let row_start = (y * width) as usize;
+                for &value in &luma[row_start + x0 as usize..row_start + x1 as usize] {
+                    histogram[value as usize] += 1;
+                }
+            }
+
+            let area = ((x1 - x0) * (y1 - y0)).max(1);
+            let limit = ((clip_limit * area as f32 / 256.0) as u32).max(1);
+
+            let mut excess = 0u32;
+            for count in histogram.iter_mut() {
+                if *count > limit {
+                    excess += *count - limit;
+                    *count = limit;
+                }
+            }
+
+            let spread = excess / 256;
+            let remainder = (excess % 256) as usize;
+            for (value, count) in histogram.iter_mut().enumerate() {
+                *count += spread + if value < remainder { 1 } else { 0 };
+            }
+
+            let mut lut = [0u8; 256];
+            let mut running = 0u32;
+            for (value, count) in histogram.iter().enumerate() {
+                running += count;
+                lut[value] = (running as f32 * 255.0 / area as f32).round().min(255.0) as u8;
+            }
+            lut
+        })
+        .collect();
+
+    send_progress(&progress_tx, 0.5);
+
+    let tile_width = width as f32 / tiles_x as f32;
+    let tile_height = height as f32 / tiles_y as f32;
+    let mut new_luma = vec![0u8; luma.len()];
+
+    new_luma
+        .par_chunks_exact_mut(width as usize)
+        .enumerate()
+        .for_each(|(y, out_row)| {
+            let grid_y = ((y as f32 + 0.5) / tile_height - 0.5).max(0.0);
+            let ty0 = (grid_y as u32).min(tiles_y - 1);
+            let ty1 = (ty0 + 1).min(tiles_y - 1);
+            let fy = (grid_y - ty0 as f32).clamp(0.0, 1.0);
+
+            for x in 0..width {
+                let grid_x = ((x as f32 + 0.5) / tile_width - 0.5).max(0.0);
+                let tx0 = (grid_x as u32).min(tiles_x - 1);
+                let tx1 = (tx0 + 1).min(tiles_x - 1);
+                let fx = (grid_x - tx0 as f32).clamp(0.0, 1.0);
+
+                let value = luma[y * width as usize + x as usize] as usize;
+                let top_left = tile_luts[(ty0 * tiles_x + tx0) as usize][value] as f32;
+                let top_right = tile_luts[(ty0 * tiles_x + tx1) as usize][value] as f32;
+                let bottom_left = tile_luts[(ty1 * tiles_x + tx0) as usize][value] as f32;
+                let bottom_right = tile_luts[(ty1 * tiles_x + tx1) as usize][value] as f32;
+
+                let top = top_left + (top_right - top_left) * fx;
+                let bottom = bottom_left + (bottom_right - bottom_left) * fx;
+                out_row[x as usize] = (top + (bottom - top) * fy).round() as u8;
+            }
+        });
+
+    let out_buffer = replace_luma(&rgb_img, &luma, &new_luma);
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+// Rec. 601 weights shared by thresholding, the luma based filters and palette matching
+pub(crate) const LUMA_WEIGHTS: [f32; 3] = [0.299, 0.587, 0.114];
+
+pub(crate) fn luma(pixel: &[u8]) -> f32 {
+    LUMA_WEIGHTS[0] * pixel[0] as f32 +
+    LUMA_WEIGHTS[1] * pixel[1] as f32 +
+    LUMA_WEIGHTS[2] * pixel[2] as f32
+}
+
+
+
+pub(crate) fn luma_plane(rgb_img: &ImageBuffer<Rgb<u8>, Vec<u8>>) -> Vec<u8> {
+    rgb_img
+        .as_raw()
+        .par_chunks_exact(3)
+        .map(|pixel| luma(pixel).round() as u8)
+        .collect()
+}
+
+
+
+// Shifting every channel by the change in luma keeps Cb/Cr fixed, so only brightness changes.
+fn replace_luma(rgb_img: &ImageBuffer<Rgb<u8>, Vec<u8>>, luma: &[u8], new_luma: &[u8]) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let (width, height) = rgb_img.dimensions();
+    let mut out_buffer = ImageBuffer::new(width, height);
+
+    let in_pixels = rgb_img.as_raw();
+    let out_pixels = out_buffer.as_mut();
+
+    in_pixels
+        .par_chunks_exact(3)
+        .zip(out_pixels.par_chunks_exact_mut(3))
+        .zip(luma.par_iter().zip(new_luma.par_iter()))
+        .for_each(|((in_pixel, out_pixel), (&old, &new))| {
+            let delta = new as i32 - old as i32;
+            out_pixel[0] = (in_pixel[0] as i32 + delta).clamp(0, 255) as u8;
+            out_pixel[1] = (in_pixel[1] as i32 + delta).clamp(0, 255) as u8;
+            out_pixel[2] = (in_pixel[2] as i32 + delta).clamp(0, 255) as u8;
+        });
+
+    out_buffer
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curve_points_parse_pairs() {
+        assert_eq!(parse_curve_points("0:0, 128:150,255:255").unwrap(), vec![(0.0, 0.0), (128.0, 150.0), (255.0, 255.0)]);
+        assert!(parse_curve_points("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn curve_points_reject_bad_input() {
+        assert!(parse_curve_points("128").is_err());
+        assert!(parse_curve_points("0:0,abc:10").is_err());
+        assert!(parse_curve_points("0:0,128:").is_err());
+        assert!(parse_curve_points("300:10").is_err());
+        assert!(parse_curve_points("10:-5").is_err());
+    }
+
+    fn noise_image(width: u32, height: u32) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        let mut state = 12345u32;
+        ImageBuffer::from_fn(width, height, |_, _| {
+            let mut next = || {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                (state >> 16) as u8
+            };
+            Rgb([next(), next(), next()])
+        })
+    }
+
+    // Minimum over the (2r+1)^2 square, ignoring pixels past the border
+    fn naive_erode(img: &ImageBuffer<Rgb<u8>, Vec<u8>>, radius: i64) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        let (width, height) = img.dimensions();
+        ImageBuffer::from_fn(width, height, |x, y| {
+            let mut minimum = [255u8; 3];
+            for ny in (y as i64 - radius).max(0)..=(y as i64 + radius).min(height as i64 - 1) {
+                for nx in (x as i64 - radius).max(0)..=(x as i64 + radius).min(width as i64 - 1) {
+                    let pixel = img.get_pixel(nx as u32, ny as u32);
+                    for channel in 0..3 {
+                        minimum[channel] = minimum[channel].min(pixel[channel]);
+                    }
+                }
+            }
+            Rgb(minimum)
+        })
+    }
+
+    #[test]
+    fn van_herk_rows_match_naive_minimum() {
+        let img = noise_image(11, 3);
+        for radius in [0, 1, 2, 5, 12] {
+            let rows = line_extreme_rows(img.as_raw(), img.width(), radius, false);
+            let expected: Vec<u8> = (0..img.height())
+                .flat_map(|y| {
+                    let row = ImageBuffer::from_fn(img.width(), 1, |x, _| *img.get_pixel(x, y));
+                    naive_erode(&row, radius as i64).into_raw()
+                })
+                .collect();
+            assert_eq!(rows, expected, "radius {}", radius);
+        }
+    }
+
+    #[test]
+    fn square_erosion_matches_naive_minimum() {
+        let img = noise_image(13, 9);
+        for radius in [1, 2, 4] {
+            let eroded = morphology(&DynamicImage::ImageRgb8(img.clone()), MorphOperation::Erode, StructuringElement::Square, radius, None);
+            assert_eq!(eroded, naive_erode(&img, radius as i64), "radius {}", radius);
+        }
+    }
+
+    #[test]
+    fn otsu_splits_bimodal_histogram() {
+        // Clusters at 30-46 and 190-210; any cut between them separates the classes
+        let luma: Vec<u8> = (0..400u32).map(|index| if index % 3 == 0 { 190 + (index % 21) as u8 } else { 30 + (index % 17) as u8 }).collect();
+        let threshold = otsu_threshold(&luma);
+        assert!((46..190).contains(&threshold), "threshold {}", threshold);
+        assert!(luma.iter().all(|&value| (value > threshold) == (value >= 190)));
+    }
+}
@@ -1,4 +1,6 @@
-use std::sync::mpsc;
+use std::{str::FromStr, sync::mpsc};
+use image::{imageops, DynamicImage, ImageBuffer, Rgb};
+use rayon::{iter::{IndexedParallelIterator, ParallelIterator}, slice::ParallelSliceMut};
 
 // Re-export all filter modules
 pub mod basic;
@@ -7,6 +9,8 @@ pub mod geometric;
 pub mod artistic;
 pub mod enhancement;
 pub mod utility;
+pub mod lut;
+pub mod quantize;
 
 // Common types and utilities
 pub type ProgressSender = mpsc::Sender<f64>;
@@ -34,6 +38,90 @@ impl Default for AsciiConfig {
     }
 }
 
+// How neighborhood filters read pixels that fall outside the image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BorderMode {
+    Clamp,
+    Mirror,
+    Wrap,
+    Constant(Rgb<u8>),
+}
+
+impl BorderMode {
+    // Maps a coordinate onto 0..len, or None where the constant color should be used instead.
+    pub fn resolve(&self, index: isize, len: usize) -> Option<usize> {
+        let last = len as isize - 1;
+        if (0..=last).contains(&index) {
+            return Some(index as usize);
+        }
+
+        match self {
+            BorderMode::Clamp => Some(index.clamp(0, last) as usize),
+            BorderMode::Mirror => {
+                // Reflect about the edge pixels without repeating them: -1 -> 1, len -> len - 2
+                if last == 0 {
+                    return Some(0);
+                }
+                let period = 2 * last;
+                let folded = index.rem_euclid(period);
+                Some(if folded > last { period - folded } else { folded } as usize)
+            }
+            BorderMode::Wrap => Some(index.rem_euclid(len as isize) as usize),
+            BorderMode::Constant(_) => None,
+        }
+    }
+
+    // Grows the image by `pad` pixels on every side, filled according to the mode.
+    pub fn pad(&self, img: &ImageBuffer<Rgb<u8>, Vec<u8>>, pad: u32) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        let (width, height) = img.dimensions();
+        let (padded_width, padded_height) = (width + 2 * pad, height + 2 * pad);
+        let fill = match self {
+            BorderMode::Constant(color) => color.0,
+            _ => [0, 0, 0],
+        };
+
+        let mut out_buffer = ImageBuffer::new(padded_width, padded_height);
+        out_buffer
+            .as_mut()
+            .par_chunks_exact_mut((padded_width * 3) as usize)
+            .enumerate()
+            .for_each(|(y, out_row)| {
+                let source_y = self.resolve(y as isize - pad as isize, height as usize);
+                for x in 0..padded_width as usize {
+                    let source_x = self.resolve(x as isize - pad as isize, width as usize);
+                    let pixel = match (source_x, source_y) {
+                        (Some(source_x), Some(source_y)) => img.get_pixel(source_x as u32, source_y as u32).0,
+                        _ => fill,
+                    };
+                    out_row[x * 3..x * 3 + 3].copy_from_slice(&pixel);
+                }
+            });
+
+        out_buffer
+    }
+}
+
+impl FromStr for BorderMode {
+    type Err = String;
+
+    // "clamp", "mirror", "wrap", "constant" (black) or "constant:#rrggbb"
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let lower = value.trim().to_lowercase();
+        match lower.split_once(':') {
+            Some(("constant", color)) => color::parse_hex_color(color)
+                .map(BorderMode::Constant)
+                .map_err(|error| error.to_string()),
+            _ => match lower.as_str() {
+                "clamp" => Ok(BorderMode::Clamp),
+                "mirror" => Ok(BorderMode::Mirror),
+                "wrap" => Ok(BorderMode::Wrap),
+                "constant" => Ok(BorderMode::Constant(Rgb([0, 0, 0]))),
+                other => Err(format!("Unknown border mode: {}", other)),
+            },
+        }
+    }
+}
+
 // Helper function to send progress updates
 pub fn send_progress(tx: &Option<ProgressSender>, progress: f64) {
     if let Some(sender) = tx {
@@ -41,123 +129,429 @@ pub fn send_progress(tx: &Option<ProgressSender>, progress: f64) {
     }
 }
 
+// Runs a neighborhood filter on a padded copy of the image and crops the result back, so every
+// filter reads outside pixels the same way no matter how it handles edges internally.
+fn with_border(
+    img: &DynamicImage,
+    pad: u32,
+    border: BorderMode,
+    filter: impl FnOnce(&DynamicImage) -> ImageBuffer<Rgb<u8>, Vec<u8>>,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let (width, height) = (img.width(), img.height());
+    let padded = DynamicImage::ImageRgb8(border.pad(&img.to_rgb8(), pad));
+    let result = filter(&padded);
+    imageops::crop_imm(&result, pad, pad, width, height).to_image()
+}
+
+// Looks up a filter parameter, so short parameter lists from free-form input (CLI, LUT pipelines)
+// fail with an error instead of panicking.
+fn param(param_values: &[String], index: usize) -> Result<&str> {
+    param_values
+        .get(index)
+        .map(String::as_str)
+        .ok_or_else(|| format!("Missing parameter {} ({} given)", index + 1, param_values.len()).into())
+}
+
 // Main filter processor function
 pub fn process_filter(
     filter_name: &str,
     input_file: &str,
     output_file: &str,
     param_values: &[String],
+    linear_light: bool,
     progress_tx: Option<ProgressSender>,
 ) -> Result<()> {
     
     let img = image::open(input_file)?;
+    let result = apply_filter(filter_name, &img, param_values, linear_light, progress_tx)?;
+    result.save(output_file)?;
+    
+    Ok(())
+}
+
+// Runs a single filter on an in-memory image
+pub fn apply_filter(
+    filter_name: &str,
+    img: &DynamicImage,
+    param_values: &[String],
+    linear_light: bool,
+    progress_tx: Option<ProgressSender>,
+) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>> {
     
-    match filter_name {
+    let result = match filter_name {
 
         "grayscale" => {
-            let result = basic::grayscale(&img, progress_tx);
-            result.save(output_file)?;
+            basic::grayscale(img, progress_tx)
         }
 
         "brightness" => {
-            let value: i32 = param_values[0].parse()?;
-            let result = enhancement::brightness(&img, value, progress_tx);
-            result.save(output_file)?;
+            let value: i32 = param(param_values, 0)?.parse()?;
+            let linear = linear_light || param(param_values, 1)?.to_lowercase().parse::<bool>()?;
+            enhancement::brightness(img, value, linear, progress_tx)
         }
         "contrast" => {
-            let factor: f32 = param_values[0].parse()?;
-            let result = enhancement::contrast(&img, factor, progress_tx);
-            result.save(output_file)?;
+            let factor: f32 = param(param_values, 0)?.parse()?;
+            let linear = linear_light || param(param_values, 1)?.to_lowercase().parse::<bool>()?;
+            enhancement::contrast(img, factor, linear, progress_tx)
+        }
+        "levels" => {
+            let in_black: u8 = param(param_values, 0)?.parse()?;
+            let in_white: u8 = param(param_values, 1)?.parse()?;
+            let gamma: f32 = param(param_values, 2)?.parse()?;
+            let out_black: u8 = param(param_values, 3)?.parse()?;
+            let out_white: u8 = param(param_values, 4)?.parse()?;
+            enhancement::levels(img, in_black, in_white, gamma, out_black, out_white, progress_tx)
+        }
+        "curves" => {
+            let master = enhancement::parse_curve_points(param(param_values, 0)?)?;
+            let red = enhancement::parse_curve_points(param(param_values, 1)?)?;
+            let green = enhancement::parse_curve_points(param(param_values, 2)?)?;
+            let blue = enhancement::parse_curve_points(param(param_values, 3)?)?;
+            enhancement::curves(img, &master, &red, &green, &blue, progress_tx)
         }
         "gaussian-blur" => {
-            let sigma: f32 = param_values[0].parse()?;
-            let result = enhancement::gaussian_blur(&img, sigma, progress_tx);
-            result.save(output_file)?;
+            let sigma: f32 = param(param_values, 0)?.parse()?;
+            let linear = linear_light || param(param_values, 1)?.to_lowercase().parse::<bool>()?;
+            let border: BorderMode = param(param_values, 2)?.parse()?;
+            with_border(img, (3.0 * sigma).ceil() as u32, border, |img| enhancement::gaussian_blur(img, sigma, linear, progress_tx))
         }
         "box-blur" => {
-            let radius: u32 = param_values[0].parse()?;
-            let result = enhancement::box_blur(&img, radius, progress_tx);
-            result.save(output_file)?;
+            let radius: u32 = param(param_values, 0)?.parse()?;
+            let linear = linear_light || param(param_values, 1)?.to_lowercase().parse::<bool>()?;
+            let border: BorderMode = param(param_values, 2)?.parse()?;
+            with_border(img, radius, border, |img| enhancement::box_blur(img, radius, linear, progress_tx))
+        }
+        "motion-blur" => {
+            let angle: f32 = param(param_values, 0)?.parse()?;
+            let length: u32 = param(param_values, 1)?.parse()?;
+            let linear = linear_light || param(param_values, 2)?.to_lowercase().parse::<bool>()?;
+            let border: BorderMode = param(param_values, 3)?.parse()?;
+            enhancement::motion_blur(img, angle, length, linear, border, progress_tx)
+        }
+        "radial-blur" => {
+            let angle: f32 = param(param_values, 0)?.parse()?;
+            let center_x: f32 = param(param_values, 1)?.parse()?;
+            let center_y: f32 = param(param_values, 2)?.parse()?;
+            let linear = linear_light || param(param_values, 3)?.to_lowercase().parse::<bool>()?;
+            let border: BorderMode = param(param_values, 4)?.parse()?;
+            enhancement::radial_blur(img, angle, (center_x, center_y), linear, border, progress_tx)
+        }
+        "zoom-blur" => {
+            let strength: f32 = param(param_values, 0)?.parse()?;
+            let center_x: f32 = param(param_values, 1)?.parse()?;
+            let center_y: f32 = param(param_values, 2)?.parse()?;
+            let linear = linear_light || param(param_values, 3)?.to_lowercase().parse::<bool>()?;
+            let border: BorderMode = param(param_values, 4)?.parse()?;
+            enhancement::zoom_blur(img, strength, (center_x, center_y), linear, border, progress_tx)
+        }
+        "tilt-shift" => {
+            let position: f32 = param(param_values, 0)?.parse()?;
+            let angle: f32 = param(param_values, 1)?.parse()?;
+            let band: f32 = param(param_values, 2)?.parse()?;
+            let falloff: f32 = param(param_values, 3)?.parse()?;
+            let max_sigma: f32 = param(param_values, 4)?.parse()?;
+            let linear = linear_light || param(param_values, 5)?.to_lowercase().parse::<bool>()?;
+            let border: BorderMode = param(param_values, 6)?.parse()?;
+            enhancement::tilt_shift(img, (position, angle, band, falloff), max_sigma, linear, border, progress_tx)
+        }
+        "mask-blur" => {
+            let mask = image::open(param(param_values, 0)?)?.to_luma8();
+            let max_sigma: f32 = param(param_values, 1)?.parse()?;
+            let invert = param(param_values, 2)?.to_lowercase().parse::<bool>()?;
+            let linear = linear_light || param(param_values, 3)?.to_lowercase().parse::<bool>()?;
+            let border: BorderMode = param(param_values, 4)?.parse()?;
+            enhancement::mask_blur(img, &mask, max_sigma, invert, linear, border, progress_tx)
+        }
+        "bilateral" => {
+            let spatial_sigma: f32 = param(param_values, 0)?.parse()?;
+            let range_sigma: f32 = param(param_values, 1)?.parse()?;
+            let fast = param(param_values, 2)?.to_lowercase().parse::<bool>()?;
+            let border: BorderMode = param(param_values, 3)?.parse()?;
+            with_border(img, (2.0 * spatial_sigma).ceil() as u32, border, |img| enhancement::bilateral(img, spatial_sigma, range_sigma, fast, progress_tx))
+        }
+        "guided-filter" => {
+            let radius: u32 = param(param_values, 0)?.parse()?;
+            let epsilon: f32 = param(param_values, 1)?.parse()?;
+            let fast = param(param_values, 2)?.to_lowercase().parse::<bool>()?;
+            let border: BorderMode = param(param_values, 3)?.parse()?;
+            with_border(img, 2 * radius, border, |img| enhancement::guided_filter(img, radius, epsilon, fast, progress_tx))
+        }
+        "median" => {
+            let radius: u32 = param(param_values, 0)?.parse()?;
+            let border: BorderMode = param(param_values, 1)?.parse()?;
+            with_border(img, radius, border, |img| enhancement::median(img, radius, progress_tx))
+        }
+        "nl-means" => {
+            let strength: f32 = param(param_values, 0)?.parse()?;
+            let patch_radius: u32 = param(param_values, 1)?.parse()?;
+            let search_radius: u32 = param(param_values, 2)?.parse()?;
+            let border: BorderMode = param(param_values, 3)?.parse()?;
+            with_border(img, patch_radius + search_radius, border, |img| enhancement::nl_means(img, strength, patch_radius, search_radius, progress_tx))
+        }
+        "convolve" => {
+            let kernel = enhancement::Kernel::from_spec(param(param_values, 0)?)?;
+            let divisor: f32 = param(param_values, 1)?.parse()?;
+            let bias: f32 = param(param_values, 2)?.parse()?;
+            let border: BorderMode = param(param_values, 3)?.parse()?;
+            enhancement::convolve(img, &kernel, divisor, bias, border, progress_tx)
         }
         "sharpen" => {
-            let strength: f32 = param_values[0].parse()?;
-            let result = enhancement::sharpen(&img, strength, progress_tx);
-            result.save(output_file)?;
+            let strength: f32 = param(param_values, 0)?.parse()?;
+            let border: BorderMode = param(param_values, 1)?.parse()?;
+            with_border(img, 1, border, |img| enhancement::sharpen(img, strength, progress_tx))
+        }
+        "unsharp-mask" => {
+            let radius: f32 = param(param_values, 0)?.parse()?;
+            let amount: f32 = param(param_values, 1)?.parse()?;
+            let threshold: u8 = param(param_values, 2)?.parse()?;
+            let luminance_only = param(param_values, 3)?.to_lowercase().parse::<bool>()?;
+            let border: BorderMode = param(param_values, 4)?.parse()?;
+            with_border(img, (3.0 * radius).ceil() as u32, border, |img| enhancement::unsharp_mask(img, radius, amount, threshold, luminance_only, progress_tx))
         }
         "edge-detection" => {
-            let result = enhancement::edge_detection(&img, progress_tx);
-            result.save(output_file)?;
+            let sigma: f32 = param(param_values, 1)?.parse()?;
+            let low: f32 = param(param_values, 2)?.parse()?;
+            let high: f32 = param(param_values, 3)?.parse()?;
+            let operator = match param(param_values, 0)? {
+                "sobel" => enhancement::EdgeOperator::Sobel,
+                "scharr" => enhancement::EdgeOperator::Scharr,
+                "prewitt" => enhancement::EdgeOperator::Prewitt,
+                "log" => enhancement::EdgeOperator::LaplacianOfGaussian { sigma, threshold: low },
+                "canny" => enhancement::EdgeOperator::Canny { sigma, low, high },
+                other => return Err(format!("Unknown edge operator: {}", other).into()),
+            };
+            let output = match param(param_values, 4)? {
+                "magnitude" => enhancement::EdgeOutput::Magnitude,
+                "direction" => enhancement::EdgeOutput::Direction,
+                "overlay" => enhancement::EdgeOutput::Overlay(color::parse_hex_color(param(param_values, 5)?)?),
+                other => return Err(format!("Unknown edge output: {}", other).into()),
+            };
+            let border: BorderMode = param(param_values, 6)?.parse()?;
+            let pad = match operator {
+                enhancement::EdgeOperator::LaplacianOfGaussian { .. } | enhancement::EdgeOperator::Canny { .. } => (3.0 * sigma).ceil() as u32 + 2,
+                _ => 1,
+            };
+            with_border(img, pad, border, |img| enhancement::edge_detection(img, operator, output, progress_tx))
         }
         "thresholding" => {
-            let threshold: u8 = param_values[0].parse()?;
-            let result = enhancement::thresholding(&img, threshold, progress_tx);
-            result.save(output_file)?;
+            let threshold: u8 = param(param_values, 0)?.parse()?;
+            enhancement::thresholding(img, threshold, progress_tx)
+        }
+        "dither" => {
+            let method: quantize::DitherMethod = param(param_values, 0)?.parse()?;
+            let levels: usize = param(param_values, 1)?.parse()?;
+            let gray = DynamicImage::ImageRgb8(basic::grayscale(img, None));
+            quantize::dither(&gray, &quantize::Palette::grayscale(levels), method, progress_tx)
+        }
+        "morphology" => {
+            let operation: enhancement::MorphOperation = param(param_values, 0)?.parse()?;
+            let element: enhancement::StructuringElement = param(param_values, 1)?.parse()?;
+            let radius: u32 = param(param_values, 2)?.parse()?;
+            let border: BorderMode = param(param_values, 3)?.parse()?;
+            // Compound operations chain two passes, each reaching `radius` further out
+            with_border(img, 2 * radius, border, |img| enhancement::morphology(img, operation, element, radius, progress_tx))
+        }
+        "adaptive-threshold" => {
+            let method: enhancement::ThresholdMethod = param(param_values, 0)?.parse()?;
+            let window: u32 = param(param_values, 1)?.parse()?;
+            let offset: f32 = param(param_values, 2)?.parse()?;
+            let k: f32 = param(param_values, 3)?.parse()?;
+            let border: BorderMode = param(param_values, 4)?.parse()?;
+            // Otsu reads one global histogram, which padding pixels would skew
+            let pad = if method == enhancement::ThresholdMethod::Otsu { 0 } else { window };
+            with_border(img, pad, border, |img| enhancement::adaptive_threshold(img, method, window, offset, k, progress_tx))
+        }
+        "histogram-equalization" => {
+            enhancement::histogram_equalization(img, progress_tx)
+        }
+        "clahe" => {
+            let grid_size: u32 = param(param_values, 0)?.parse()?;
+            let clip_limit: f32 = param(param_values, 1)?.parse()?;
+            enhancement::clahe(img, grid_size, clip_limit, progress_tx)
         }
 
         "saturate" => {
-            let factor: f32 = param_values[0].parse()?;
-            let result = color::saturate(&img, factor, progress_tx);
-            result.save(output_file)?;
+            let factor: f32 = param(param_values, 0)?.parse()?;
+            let space: color::ColorSpace = param(param_values, 1)?.parse()?;
+            color::saturate(img, factor, space, progress_tx)
+        }
+        "vibrance" => {
+            let amount: f32 = param(param_values, 0)?.parse()?;
+            let protect_skin = param(param_values, 1)?.to_lowercase().parse::<bool>()?;
+            color::vibrance(img, amount, protect_skin, progress_tx)
+        }
+        "channel-mixer" => {
+            let matrix: color::ChannelMatrix = param(param_values, 0)?.parse()?;
+            color::channel_mixer(img, &matrix, progress_tx)
+        }
+        "channel-adjust" => {
+            let brightness: [i32; 3] = [param(param_values, 0)?.parse()?, param(param_values, 1)?.parse()?, param(param_values, 2)?.parse()?];
+            let contrast: [f32; 3] = [param(param_values, 3)?.parse()?, param(param_values, 4)?.parse()?, param(param_values, 5)?.parse()?];
+            let gamma: [f32; 3] = [param(param_values, 6)?.parse()?, param(param_values, 7)?.parse()?, param(param_values, 8)?.parse()?];
+            color::channel_adjust(img, brightness, contrast, gamma, progress_tx)
+        }
+        "cube-lut" => {
+            let lut = lut::CubeLut::load(param(param_values, 0)?)?;
+            let interpolation: lut::LutInterpolation = param(param_values, 1)?.parse()?;
+            lut::apply_cube(img, &lut, interpolation, progress_tx)
+        }
+        "quantize" => {
+            let colors: usize = param(param_values, 0)?.parse()?;
+            let method: quantize::QuantizeMethod = param(param_values, 1)?.parse()?;
+            let dither: quantize::DitherMethod = param(param_values, 2)?.parse()?;
+            let (quantized, palette) = quantize::quantize(img, colors, method, dither, progress_tx);
+            if param(param_values, 3)? != "none" {
+                palette.save_indexed_png(&quantized, param(param_values, 3)?)?;
+            }
+            quantized
+        }
+        "palette-map" => {
+            let palette = quantize::Palette::load(param(param_values, 0)?)?;
+            let dither: quantize::DitherMethod = param(param_values, 1)?.parse()?;
+            let mapped = quantize::dither(img, &palette, dither, progress_tx);
+            if param(param_values, 2)? != "none" {
+                palette.save_indexed_png(&mapped, param(param_values, 2)?)?;
+            }
+            mapped
         }
         "invert" => {
-            let result = color::invert(&img, progress_tx);
-            result.save(output_file)?;
+            color::invert(img, progress_tx)
         }
         "hue-rotate" => {
-            let degrees: f32 = param_values[0].parse()?;
-            let result = color::hue_rotate(&img, degrees, progress_tx);
-            result.save(output_file)?;
+            let degrees: f32 = param(param_values, 0)?.parse()?;
+            let space: color::ColorSpace = param(param_values, 1)?.parse()?;
+            color::hue_rotate(img, degrees, space, progress_tx)
+        }
+        "lightness" => {
+            let amount: f32 = param(param_values, 0)?.parse()?;
+            let space: color::ColorSpace = param(param_values, 1)?.parse()?;
+            color::lightness(img, amount, space, progress_tx)
+        }
+        "white-balance" => {
+            let temperature: f32 = param(param_values, 1)?.parse()?;
+            let tint: f32 = param(param_values, 2)?.parse()?;
+            let x: u32 = param(param_values, 3)?.parse()?;
+            let y: u32 = param(param_values, 4)?.parse()?;
+            let mode = match param(param_values, 0)? {
+                "manual" => color::WhiteBalance::Manual { temperature, tint },
+                "gray-world" => color::WhiteBalance::GrayWorld,
+                "white-patch" => color::WhiteBalance::WhitePatch,
+                "gray-point" => color::WhiteBalance::GrayPoint { x, y },
+                other => return Err(format!("Unknown white balance mode: {}", other).into()),
+            };
+            color::white_balance(img, mode, progress_tx)
         }
 
         "rotate90" => {
-            let result = geometric::rotate90(&img, progress_tx);
-            result.save(output_file)?;
+            geometric::rotate90(img, progress_tx)
         }
         "rotate180" => {
-            let result = geometric::rotate180(&img, progress_tx);
-            result.save(output_file)?;
+            geometric::rotate180(img, progress_tx)
         }
         "rotate270" => {
-            let result = geometric::rotate270(&img, progress_tx);
-            result.save(output_file)?;
+            geometric::rotate270(img, progress_tx)
         }
         "flip-horizontal" => {
-            let result = geometric::flip_horizontal(&img, progress_tx);
-            result.save(output_file)?;
+            geometric::flip_horizontal(img, progress_tx)
         }
         "flip-vertical" => {
-            let result = geometric::flip_vertical(&img, progress_tx);
-            result.save(output_file)?;
+            geometric::flip_vertical(img, progress_tx)
         }
 
         "sepia" => {
-            let result = artistic::sepia(&img, progress_tx);
-            result.save(output_file)?;
+            artistic::sepia(img, progress_tx)
+        }
+        "gradient-map" => {
+            let stops = artistic::parse_gradient_stops(param(param_values, 0)?)?;
+            artistic::gradient_map(img, &stops, progress_tx)
+        }
+        "duotone" => {
+            let shadow = color::parse_hex_color(param(param_values, 0)?)?;
+            let highlight = color::parse_hex_color(param(param_values, 1)?)?;
+            artistic::duotone(img, shadow, highlight, progress_tx)
+        }
+        "tritone" => {
+            let shadow = color::parse_hex_color(param(param_values, 0)?)?;
+            let midtone = color::parse_hex_color(param(param_values, 1)?)?;
+            let highlight = color::parse_hex_color(param(param_values, 2)?)?;
+            artistic::tritone(img, shadow, midtone, highlight, progress_tx)
         }
         "vignette" => {
-            let strength: f32 = param_values[0].parse()?;
-            let result = artistic::vignette(&img, strength, progress_tx);
-            result.save(output_file)?;
+            let strength: f32 = param(param_values, 0)?.parse()?;
+            let linear = linear_light || param(param_values, 1)?.to_lowercase().parse::<bool>()?;
+            artistic::vignette(img, strength, linear, progress_tx)
         }
         "noise" => {
-            let strength: u8 = param_values[0].parse()?;
-            let result = artistic::noise(&img, strength, progress_tx);
-            result.save(output_file)?;
+            let strength: u8 = param(param_values, 0)?.parse()?;
+            artistic::noise(img, strength, progress_tx)
+        }
+        "pixelate" => {
+            let block_size: u32 = param(param_values, 0)?.parse()?;
+            let mode: artistic::PixelateMode = param(param_values, 1)?.parse()?;
+            let x: u32 = param(param_values, 2)?.parse()?;
+            let y: u32 = param(param_values, 3)?.parse()?;
+            let width: u32 = param(param_values, 4)?.parse()?;
+            let height: u32 = param(param_values, 5)?.parse()?;
+            artistic::pixelate(img, block_size, mode, (x, y, width, height), progress_tx)
         }
         "oil" => {
-            let radius: u32 = param_values[0].parse()?;
-            let intensity: u32 = param_values[1].parse()?;
-            let result = artistic::oil_painting(&img, radius, intensity, progress_tx);
-            result.save(output_file)?;
+            let radius: u32 = param(param_values, 0)?.parse()?;
+            let intensity: u32 = param(param_values, 1)?.parse()?;
+            let border: BorderMode = param(param_values, 2)?.parse()?;
+            with_border(img, radius, border, |img| artistic::oil_painting(img, radius, intensity, progress_tx))
+        }
+        "emboss" => {
+            let angle: f32 = param(param_values, 0)?.parse()?;
+            let elevation: f32 = param(param_values, 1)?.parse()?;
+            let depth: f32 = param(param_values, 2)?.parse()?;
+            let output: artistic::EmbossOutput = param(param_values, 3)?.parse()?;
+            let border: BorderMode = param(param_values, 4)?.parse()?;
+            with_border(img, 1, border, |img| artistic::emboss(img, angle, elevation, depth, output, progress_tx))
+        }
+        "cartoon" => {
+            let smoothing: f32 = param(param_values, 0)?.parse()?;
+            let levels: u32 = param(param_values, 1)?.parse()?;
+            let edge_threshold: f32 = param(param_values, 2)?.parse()?;
+            let edge_thickness: u32 = param(param_values, 3)?.parse()?;
+            let border: BorderMode = param(param_values, 4)?.parse()?;
+            with_border(img, 8 + edge_thickness, border, |img| artistic::cartoon(img, smoothing, levels, edge_threshold, edge_thickness, progress_tx))
+        }
+        "pencil-sketch" => {
+            let stroke_width: f32 = param(param_values, 0)?.parse()?;
+            let shading: f32 = param(param_values, 1)?.parse()?;
+            let border: BorderMode = param(param_values, 2)?.parse()?;
+            with_border(img, (3.0 * stroke_width).ceil() as u32, border, |img| artistic::pencil_sketch(img, stroke_width, shading, progress_tx))
+        }
+        "halftone" => {
+            let cell_size: f32 = param(param_values, 0)?.parse()?;
+            let angles = param(param_values, 2)?
+                .split(',')
+                .map(|angle| angle.trim().parse::<f32>())
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let inks = match param(param_values, 1)?.to_lowercase().as_str() {
+                "cmyk" => match angles[..] {
+                    [cyan, magenta, yellow, black] => artistic::HalftoneInks::Cmyk([cyan, magenta, yellow, black]),
+                    _ => return Err("CMYK halftone needs four screen angles (cyan, magenta, yellow, black)".into()),
+                },
+                "single" => artistic::HalftoneInks::Single { angle: angles[0], color: color::parse_hex_color(param(param_values, 4)?)? },
+                other => return Err(format!("Unknown halftone inks: {}", other).into()),
+            };
+            let shape: artistic::DotShape = param(param_values, 3)?.parse()?;
+            artistic::halftone(img, cell_size, inks, shape, progress_tx)
         }
 
         "crop" => {
-            let x: u32 = param_values[0].parse()?;
-            let y: u32 = param_values[1].parse()?;
-            let width: u32 = param_values[2].parse()?;
-            let height: u32 = param_values[3].parse()?;
-            let result = utility::crop(&img, x, y, width, height, progress_tx);
-            result.save(output_file)?;
+            let x: u32 = param(param_values, 0)?.parse()?;
+            let y: u32 = param(param_values, 1)?.parse()?;
+            let width: u32 = param(param_values, 2)?.parse()?;
+            let height: u32 = param(param_values, 3)?.parse()?;
+            utility::crop(img, x, y, width, height, progress_tx)
+        }
+        "export-cube" => {
+            let size: usize = param(param_values, 1)?.parse()?;
+            let baked = lut::bake_pipeline(param(param_values, 0)?, size, linear_light)?;
+            baked.save(param(param_values, 2)?)?;
+            lut::apply_cube(img, &baked, lut::LutInterpolation::Tetrahedral, progress_tx)
         }
         // "ascii" => {
         //     let config = AsciiConfig::default();
@@ -168,7 +562,36 @@ pub fn process_filter(
         _ => {
             return Err(format!("Unknown filter: {}", filter_name).into());
         }
-    }
+    };
     
-    Ok(())
-}
\ No newline at end of file
+    Ok(result)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn border_modes_resolve_out_of_range_indices() {
+        let resolve = |mode: BorderMode| [-3, -1, 0, 4, 5, 7].map(|index| mode.resolve(index, 5));
+        assert_eq!(resolve(BorderMode::Clamp), [Some(0), Some(0), Some(0), Some(4), Some(4), Some(4)]);
+        assert_eq!(resolve(BorderMode::Mirror), [Some(3), Some(1), Some(0), Some(4), Some(3), Some(1)]);
+        assert_eq!(resolve(BorderMode::Wrap), [Some(2), Some(4), Some(0), Some(4), Some(0), Some(2)]);
+        assert_eq!(resolve(BorderMode::Constant(Rgb([0, 0, 0]))), [None, None, Some(0), Some(4), None, None]);
+    }
+
+    #[test]
+    fn mirror_border_handles_single_pixel() {
+        assert_eq!(BorderMode::Mirror.resolve(-2, 1), Some(0));
+        assert_eq!(BorderMode::Mirror.resolve(3, 1), Some(0));
+    }
+
+    #[test]
+    fn border_modes_parse() {
+        assert_eq!("mirror".parse::<BorderMode>(), Ok(BorderMode::Mirror));
+        assert_eq!("constant:#ff8000".parse::<BorderMode>(), Ok(BorderMode::Constant(Rgb([255, 128, 0]))));
+        assert!("reflect".parse::<BorderMode>().is_err());
+    }
+}
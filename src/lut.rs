@@ -0,0 +1,409 @@
+use std::{fmt::Write, fs, path::Path, str::FromStr};
+use image::{DynamicImage, ImageBuffer, Rgb};
+use rayon::{iter::{IndexedParallelIterator, ParallelIterator}, slice::{ParallelSlice, ParallelSliceMut}};
+use crate::{apply_filter, enhancement::apply_luts, ProgressSender, Result, send_progress};
+
+// Filters that map each pixel independently of its neighbours and of image statistics,
+// so running them over an identity lattice captures them exactly.
+pub const BAKEABLE_FILTERS: &[&str] = &[
+    "grayscale",
+    "brightness",
+    "contrast",
+    "levels",
+    "curves",
+    "thresholding",
+    "saturate",
+    "vibrance",
+    "hue-rotate",
+    "lightness",
+    "white-balance",
+    "invert",
+    "channel-mixer",
+    "channel-adjust",
+    "sepia",
+    "gradient-map",
+    "duotone",
+    "tritone",
+];
+
+// Largest 3D lattice accepted from a file; real-world LUTs stop at 65 or so.
+const MAX_3D_SIZE: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LutInterpolation {
+    Trilinear,
+    Tetrahedral,
+}
+
+impl FromStr for LutInterpolation {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "trilinear" => Ok(LutInterpolation::Trilinear),
+            "tetrahedral" => Ok(LutInterpolation::Tetrahedral),
+            other => Err(format!("Unknown LUT interpolation: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CubeTable {
+    OneD(Vec<[f32; 3]>),
+    ThreeD { size: usize, entries: Vec<[f32; 3]> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CubeLut {
+    pub title: Option<String>,
+    pub domain_min: [f32; 3],
+    pub domain_max: [f32; 3],
+    pub table: CubeTable,
+}
+
+impl CubeLut {
+    pub fn load(path: impl AsRef<Path>) -> Result<CubeLut> {
+        let source = fs::read_to_string(path)?;
+        CubeLut::parse(&source)
+    }
+
+    pub fn parse(source: &str) -> Result<CubeLut> {
+        let mut title = None;
+        let mut size_1d = None;
+        let mut size_3d = None;
+        let mut domain_min = [0.0f32; 3];
+        let mut domain_max = [1.0f32; 3];
+        let mut entries = Vec::new();
+
+        for (line_number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            let numbers = || -> Result<Vec<f32>> {
+                rest.split_whitespace()
+                    .map(|value| value.parse::<f32>().map_err(|_| format!("Line {}: invalid number '{}'", line_number + 1, value).into()))
+                    .collect()
+            };
+
+            match keyword {
+                "TITLE" => title = Some(rest.trim_matches('"').to_string()),
+                "LUT_1D_SIZE" => size_1d = Some(rest.parse::<usize>()?),
+                "LUT_3D_SIZE" => size_3d = Some(rest.parse::<usize>()?),
+                "DOMAIN_MIN" | "DOMAIN_MAX" => {
+                    let values = numbers()?;
+                    if values.len() != 3 {
+                        return Err(format!("Line {}: {} needs three values", line_number + 1, keyword).into());
+                    }
+                    let target = if keyword == "DOMAIN_MIN" { &mut domain_min } else { &mut domain_max };
+                    target.copy_from_slice(&values);
+                }
+                "LUT_1D_INPUT_RANGE" | "LUT_3D_INPUT_RANGE" => {
+                    let values = numbers()?;
+                    if values.len() != 2 {
+                        return Err(format!("Line {}: {} needs two values", line_number + 1, keyword).into());
+                    }
+                    domain_min = [values[0]; 3];
+                    domain_max = [values[1]; 3];
+                }
+                _ if keyword.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.' || c == '+') => {
+                    let values: Vec<f32> = line
+                        .split_whitespace()
+                        .map(|value| value.parse::<f32>())
+                        .collect::<std::result::Result<_, _>>()
+                        .map_err(|_| format!("Line {}: invalid table entry", line_number + 1))?;
+                    if values.len() != 3 {
+                        return Err(format!("Line {}: table entries need three values", line_number + 1).into());
+                    }
+                    entries.push([values[0], values[1], values[2]]);
+                }
+                _ => {}
+            }
+        }
+
+        let table = match (size_1d, size_3d) {
+            (_, Some(size)) => {
+                // Checked before cubing so a bogus size in the file can't overflow
+                if !(2..=MAX_3D_SIZE).contains(&size) {
+                    return Err(format!("LUT_3D_SIZE must be between 2 and {}, got {}", MAX_3D_SIZE, size).into());
+                }
+                if entries.len() != size * size * size {
+                    return Err(format!("LUT_3D_SIZE {} expects {} entries, found {}", size, size * size * size, entries.len()).into());
+                }
+                CubeTable::ThreeD { size, entries }
+            }
+            (Some(size), None) => {
+                if size < 2 || entries.len() != size {
+                    return Err(format!("LUT_1D_SIZE {} expects {} entries, found {}", size, size, entries.len()).into());
+                }
+                CubeTable::OneD(entries)
+            }
+            (None, None) => return Err("Missing LUT_1D_SIZE or LUT_3D_SIZE".into()),
+        };
+
+        Ok(CubeLut { title, domain_min, domain_max, table })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, self.to_cube_string())?;
+        Ok(())
+    }
+
+    pub fn to_cube_string(&self) -> String {
+        let mut output = String::new();
+
+        if let Some(title) = &self.title {
+            let _ = writeln!(output, "TITLE \"{}\"", title);
+        }
+        let entries = match &self.table {
+            CubeTable::OneD(entries) => {
+                let _ = writeln!(output, "LUT_1D_SIZE {}", entries.len());
+                entries
+            }
+            CubeTable::ThreeD { size, entries } => {
+                let _ = writeln!(output, "LUT_3D_SIZE {}", size);
+                entries
+            }
+        };
+        let _ = writeln!(output, "DOMAIN_MIN {} {} {}", self.domain_min[0], self.domain_min[1], self.domain_min[2]);
+        let _ = writeln!(output, "DOMAIN_MAX {} {} {}", self.domain_max[0], self.domain_max[1], self.domain_max[2]);
+        output.push('\n');
+
+        for entry in entries {
+            let _ = writeln!(output, "{:.6} {:.6} {:.6}", entry[0], entry[1], entry[2]);
+        }
+        output
+    }
+
+    #[inline(always)]
+    fn normalize(&self, channel: usize, value: u8) -> f32 {
+        let range = (self.domain_max[channel] - self.domain_min[channel]).max(f32::EPSILON);
+        ((value as f32 / 255.0 - self.domain_min[channel]) / range).clamp(0.0, 1.0)
+    }
+}
+
+
+
+pub fn apply_cube(img: &DynamicImage, lut: &CubeLut, interpolation: LutInterpolation, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+
+    send_progress(&progress_tx, 0.0);
+
+    let out_buffer = match &lut.table {
+        CubeTable::OneD(entries) => {
+            let last = (entries.len() - 1) as f32;
+            let mut luts = [[0u8; 256]; 3];
+            for (channel, channel_lut) in luts.iter_mut().enumerate() {
+                for (value, entry) in channel_lut.iter_mut().enumerate() {
+                    let position = lut.normalize(channel, value as u8) * last;
+                    let index = (position as usize).min(entries.len() - 2);
+                    let fraction = position - index as f32;
+                    let mapped = entries[index][channel] + (entries[index + 1][channel] - entries[index][channel]) * fraction;
+                    *entry = (mapped * 255.0).round().clamp(0.0, 255.0) as u8;
+                }
+            }
+            apply_luts(&rgb_img, &luts)
+        }
+        CubeTable::ThreeD { size, entries } => {
+            let mut out_buffer = ImageBuffer::new(width, height);
+            let in_pixels = rgb_img.as_raw();
+            let out_pixels = out_buffer.as_mut();
+
+            in_pixels
+                .par_chunks_exact(3)
+                .zip(out_pixels.par_chunks_exact_mut(3))
+                .for_each(|(in_pixel, out_pixel)| {
+                    let coords = [
+                        lut.normalize(0, in_pixel[0]),
+                        lut.normalize(1, in_pixel[1]),
+                        lut.normalize(2, in_pixel[2]),
+                    ];
+                    let mapped = sample_3d(entries, *size, coords, interpolation);
+
+                    out_pixel[0] = (mapped[0] * 255.0).round().clamp(0.0, 255.0) as u8;
+                    out_pixel[1] = (mapped[1] * 255.0).round().clamp(0.0, 255.0) as u8;
+                    out_pixel[2] = (mapped[2] * 255.0).round().clamp(0.0, 255.0) as u8;
+                });
+            out_buffer
+        }
+    };
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+// Entries are stored with red changing fastest, then green, then blue.
+#[inline(always)]
+fn sample_3d(entries: &[[f32; 3]], size: usize, coords: [f32; 3], interpolation: LutInterpolation) -> [f32; 3] {
+    let last = (size - 1) as f32;
+    let mut base = [0usize; 3];
+    let mut fraction = [0.0f32; 3];
+    for axis in 0..3 {
+        let position = coords[axis] * last;
+        base[axis] = (position as usize).min(size - 2);
+        fraction[axis] = position - base[axis] as f32;
+    }
+
+    let at = |dr: usize, dg: usize, db: usize| -> [f32; 3] {
+        entries[(base[0] + dr) + (base[1] + dg) * size + (base[2] + db) * size * size]
+    };
+    let blend = |weights: [(f32, [f32; 3]); 4]| -> [f32; 3] {
+        let mut out = [0.0f32; 3];
+        for (weight, value) in weights {
+            for channel in 0..3 {
+                out[channel] += weight * value[channel];
+            }
+        }
+        out
+    };
+
+    let [fr, fg, fb] = fraction;
+
+    match interpolation {
+        LutInterpolation::Trilinear => {
+            let lerp = |a: [f32; 3], b: [f32; 3], t: f32| [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t];
+            let c00 = lerp(at(0, 0, 0), at(1, 0, 0), fr);
+            let c10 = lerp(at(0, 1, 0), at(1, 1, 0), fr);
+            let c01 = lerp(at(0, 0, 1), at(1, 0, 1), fr);
+            let c11 = lerp(at(0, 1, 1), at(1, 1, 1), fr);
+            lerp(lerp(c00, c10, fg), lerp(c01, c11, fg), fb)
+        }
+        LutInterpolation::Tetrahedral => {
+            let c000 = at(0, 0, 0);
+            let c111 = at(1, 1, 1);
+            if fr > fg {
+                if fg > fb {
+                    blend([(1.0 - fr, c000), (fr - fg, at(1, 0, 0)), (fg - fb, at(1, 1, 0)), (fb, c111)])
+                } else if fr > fb {
+                    blend([(1.0 - fr, c000), (fr - fb, at(1, 0, 0)), (fb - fg, at(1, 0, 1)), (fg, c111)])
+                } else {
+                    blend([(1.0 - fb, c000), (fb - fr, at(0, 0, 1)), (fr - fg, at(1, 0, 1)), (fg, c111)])
+                }
+            } else if fb > fg {
+                blend([(1.0 - fb, c000), (fb - fg, at(0, 0, 1)), (fg - fr, at(0, 1, 1)), (fr, c111)])
+            } else if fb > fr {
+                blend([(1.0 - fg, c000), (fg - fb, at(0, 1, 0)), (fb - fr, at(0, 1, 1)), (fr, c111)])
+            } else {
+                blend([(1.0 - fg, c000), (fg - fr, at(0, 1, 0)), (fr - fb, at(1, 1, 0)), (fb, c111)])
+            }
+        }
+    }
+}
+
+
+
+// Bakes a pipeline such as "saturate 1.3 oklch | curves 0:0,128:150,255:255 0:0,255:255 0:0,255:255 0:0,255:255"
+// into a 3D LUT by running each stage over an identity lattice. Filters only produce 8-bit output,
+// so lattice inputs are rounded to the nearest u8 and every stage's result is quantized to 8 bits;
+// a baked LUT can differ from running the chain directly by a level or two, plus interpolation error.
+pub fn bake_pipeline(pipeline: &str, size: usize, linear_light: bool) -> Result<CubeLut> {
+    if !(2..=65).contains(&size) {
+        return Err(format!("LUT size must be between 2 and 65, got {}", size).into());
+    }
+
+    let stages: Vec<(String, Vec<String>)> = pipeline
+        .split('|')
+        .map(str::trim)
+        .filter(|stage| !stage.is_empty())
+        .map(|stage| {
+            let mut parts = stage.split_whitespace().map(str::to_string);
+            let name = parts.next().unwrap_or_default();
+            (name, parts.collect())
+        })
+        .collect();
+
+    if stages.is_empty() {
+        return Err("Pipeline is empty".into());
+    }
+    for (name, params) in &stages {
+        if !BAKEABLE_FILTERS.contains(&name.as_str()) {
+            return Err(format!("Filter '{}' can't be baked into a LUT", name).into());
+        }
+        if name == "white-balance" && params.first().map(String::as_str) != Some("manual") {
+            return Err("white-balance can only be baked in manual mode".into());
+        }
+    }
+
+    let lattice_width = (size * size) as u32;
+    let step = 255.0 / (size - 1) as f32;
+    let lattice = ImageBuffer::from_fn(lattice_width, size as u32, |x, y| {
+        let index = (y * lattice_width + x) as usize;
+        let red = index % size;
+        let green = (index / size) % size;
+        let blue = index / (size * size);
+        Rgb([
+            (red as f32 * step).round() as u8,
+            (green as f32 * step).round() as u8,
+            (blue as f32 * step).round() as u8,
+        ])
+    });
+
+    let mut current = DynamicImage::ImageRgb8(lattice);
+    for (name, params) in &stages {
+        let baked = apply_filter(name, &current, params, linear_light, None).map_err(|error| format!("{}: {}", name, error))?;
+        current = DynamicImage::ImageRgb8(baked);
+    }
+
+    let entries = current
+        .to_rgb8()
+        .pixels()
+        .map(|pixel| [pixel[0] as f32 / 255.0, pixel[1] as f32 / 255.0, pixel[2] as f32 / 255.0])
+        .collect();
+
+    Ok(CubeLut {
+        title: Some(format!("moggu: {}", pipeline.trim())),
+        domain_min: [0.0; 3],
+        domain_max: [1.0; 3],
+        table: CubeTable::ThreeD { size, entries },
+    })
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_lut() -> CubeLut {
+        // Red varies fastest, as in the .cube format
+        let entries = (0..8).map(|index| [(index & 1) as f32, ((index >> 1) & 1) as f32 * 0.5, (index >> 2) as f32 * 0.25]).collect();
+        CubeLut {
+            title: Some("Round trip".to_string()),
+            domain_min: [0.0, 0.0, 0.125],
+            domain_max: [1.0, 0.75, 1.0],
+            table: CubeTable::ThreeD { size: 2, entries },
+        }
+    }
+
+    #[test]
+    fn cube_string_round_trips() {
+        let lut = sample_lut();
+        assert_eq!(CubeLut::parse(&lut.to_cube_string()).unwrap(), lut);
+
+        let one_d = CubeLut { table: CubeTable::OneD(vec![[0.0; 3], [0.5, 0.25, 0.75], [1.0; 3]]), ..sample_lut() };
+        assert_eq!(CubeLut::parse(&one_d.to_cube_string()).unwrap(), one_d);
+    }
+
+    #[test]
+    fn cube_file_round_trips() {
+        let path = std::env::temp_dir().join(format!("moggu-round-trip-{}.cube", std::process::id()));
+        let lut = sample_lut();
+        lut.save(&path).unwrap();
+        let loaded = CubeLut::load(&path);
+        let _ = fs::remove_file(&path);
+        assert_eq!(loaded.unwrap(), lut);
+    }
+
+    #[test]
+    fn cube_parse_rejects_bad_sizes() {
+        assert!(CubeLut::parse("LUT_3D_SIZE 2\n0 0 0\n1 1 1\n").is_err());
+        assert!(CubeLut::parse("LUT_3D_SIZE 100000000\n0 0 0\n").is_err());
+        assert!(CubeLut::parse("0 0 0\n1 1 1\n").is_err());
+    }
+}
@@ -3,7 +3,7 @@ use std::sync::mpsc;
 use std::thread;
 
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -79,6 +79,19 @@ pub enum ParamType {
   Integer { min: i32, max: i32},
   Float {min: f32, max: f32},
   Boolean,
+  Text,
+  Choice { options: Vec<String> },
+  File,
+  Color,
+  Border,
+}
+
+// Shared by every filter that snaps colors to a palette
+fn dither_options() -> Vec<String> {
+  ["none", "floyd-steinberg", "atkinson", "jjn", "sierra", "bayer2", "bayer4", "bayer8", "blue-noise"]
+    .iter()
+    .map(|option| option.to_string())
+    .collect()
 }
 
 #[derive(Debug, Clone)]
@@ -111,6 +124,8 @@ pub struct App {
   pub image_preview: Option<String>,
   pub has_image_support: bool,
   pub progress_receiver: Option<mpsc::Receiver<f64>>,
+  pub result_receiver: Option<mpsc::Receiver<std::result::Result<(), String>>>,
+  pub linear_light: bool,
 }
 
 #[derive(Debug)]
@@ -135,153 +150,1323 @@ impl App {
         name: "brightness".to_string(),
         description: "Adjust image brightness". to_string(),
         requires_param: true,
-        params: vec![FilterParam {
-          name: "value".to_string(),
-          param_type: ParamType::Integer { min: -100, max: 100 },
-          default: "20".to_string(),
-          description: "Brightness adjustment (-100 to 100)".to_string()
-        }],
+        params: vec![
+            FilterParam {
+                name: "value".to_string(),
+                param_type: ParamType::Integer { min: -100, max: 100 },
+                default: "20".to_string(),
+                description: "Brightness adjustment (-100 to 100)".to_string(),
+            },
+            FilterParam {
+                name: "Linear Light".to_string(),
+                param_type: ParamType::Boolean,
+                default: "false".to_string(),
+                description: "Process in linear light instead of sRGB (true or false)".to_string(),
+            },
+        ],
         category: FilterCategory::Enhancement,
         icon: ""
       },
       Filter {
-        name : "contrast".to_string(),
-        description: "Adjust image contrast".to_string(),
+        name : "contrast".to_string(),
+        description: "Adjust image contrast".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "factor".to_string(),
+                param_type: ParamType::Float { min: 0.1, max: 3.0 },
+                default: "1.5".to_string(),
+                description: "Contrast factor (0.1 to 3.0)".to_string(),
+            },
+            FilterParam {
+                name: "Linear Light".to_string(),
+                param_type: ParamType::Boolean,
+                default: "false".to_string(),
+                description: "Process in linear light instead of sRGB (true or false)".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "levels".to_string(),
+        description: "Remap black point, white point and midtone gamma".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Input Black".to_string(),
+                param_type: ParamType::Integer { min: 0, max: 254 },
+                default: "0".to_string(),
+                description: "Input black point (0 to 254)".to_string(),
+            },
+            FilterParam {
+                name: "Input White".to_string(),
+                param_type: ParamType::Integer { min: 1, max: 255 },
+                default: "255".to_string(),
+                description: "Input white point (1 to 255)".to_string(),
+            },
+            FilterParam {
+                name: "Gamma".to_string(),
+                param_type: ParamType::Float { min: 0.1, max: 10.0 },
+                default: "1.0".to_string(),
+                description: "Midtone gamma, above 1.0 brightens (0.1 to 10.0)".to_string(),
+            },
+            FilterParam {
+                name: "Output Black".to_string(),
+                param_type: ParamType::Integer { min: 0, max: 255 },
+                default: "0".to_string(),
+                description: "Output black level (0 to 255)".to_string(),
+            },
+            FilterParam {
+                name: "Output White".to_string(),
+                param_type: ParamType::Integer { min: 0, max: 255 },
+                default: "255".to_string(),
+                description: "Output white level (0 to 255)".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "curves".to_string(),
+        description: "Tone curves with monotone spline interpolation".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Master Curve".to_string(),
+                param_type: ParamType::Text,
+                default: "0:0,64:56,192:200,255:255".to_string(),
+                description: "Control points as input:output pairs, comma separated".to_string(),
+            },
+            FilterParam {
+                name: "Red Curve".to_string(),
+                param_type: ParamType::Text,
+                default: "0:0,255:255".to_string(),
+                description: "Red channel control points".to_string(),
+            },
+            FilterParam {
+                name: "Green Curve".to_string(),
+                param_type: ParamType::Text,
+                default: "0:0,255:255".to_string(),
+                description: "Green channel control points".to_string(),
+            },
+            FilterParam {
+                name: "Blue Curve".to_string(),
+                param_type: ParamType::Text,
+                default: "0:0,255:255".to_string(),
+                description: "Blue channel control points".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "gaussian-blur".to_string(),
+        description: "Apply Gaussian blur".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Blur Sigma".to_string(),
+                param_type: ParamType::Float { min: 0.1, max: 20.0 },
+                default: "2.0".to_string(),
+                description: "Blur intensity (0.1 to 20.0)".to_string(),
+            },
+            FilterParam {
+                name: "Linear Light".to_string(),
+                param_type: ParamType::Boolean,
+                default: "false".to_string(),
+                description: "Process in linear light instead of sRGB (true or false)".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "box-blur".to_string(),
+        description: "Apply box blur".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Blur Radius".to_string(),
+                param_type: ParamType::Integer { min: 1, max: 50 },
+                default: "5".to_string(),
+                description: "Blur radius (1 to 50)".to_string(),
+            },
+            FilterParam {
+                name: "Linear Light".to_string(),
+                param_type: ParamType::Boolean,
+                default: "false".to_string(),
+                description: "Process in linear light instead of sRGB (true or false)".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "motion-blur".to_string(),
+        description: "Blur along a direction like a moving camera".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Angle".to_string(),
+                param_type: ParamType::Float { min: -180.0, max: 180.0 },
+                default: "0.0".to_string(),
+                description: "Direction of motion in degrees (0 is horizontal)".to_string(),
+            },
+            FilterParam {
+                name: "Length".to_string(),
+                param_type: ParamType::Integer { min: 1, max: 200 },
+                default: "20".to_string(),
+                description: "Streak length in pixels (1 to 200)".to_string(),
+            },
+            FilterParam {
+                name: "Linear Light".to_string(),
+                param_type: ParamType::Boolean,
+                default: "false".to_string(),
+                description: "Process in linear light instead of sRGB (true or false)".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "radial-blur".to_string(),
+        description: "Spin blur around a center point".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Angle".to_string(),
+                param_type: ParamType::Float { min: 0.0, max: 90.0 },
+                default: "10.0".to_string(),
+                description: "Arc swept around the center in degrees (0 to 90)".to_string(),
+            },
+            FilterParam {
+                name: "Center X".to_string(),
+                param_type: ParamType::Float { min: 0.0, max: 1.0 },
+                default: "0.5".to_string(),
+                description: "Horizontal center as a fraction of the width (0.0 to 1.0)".to_string(),
+            },
+            FilterParam {
+                name: "Center Y".to_string(),
+                param_type: ParamType::Float { min: 0.0, max: 1.0 },
+                default: "0.5".to_string(),
+                description: "Vertical center as a fraction of the height (0.0 to 1.0)".to_string(),
+            },
+            FilterParam {
+                name: "Linear Light".to_string(),
+                param_type: ParamType::Boolean,
+                default: "false".to_string(),
+                description: "Process in linear light instead of sRGB (true or false)".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "zoom-blur".to_string(),
+        description: "Blur streaking towards a center point".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Strength".to_string(),
+                param_type: ParamType::Float { min: 0.0, max: 1.0 },
+                default: "0.2".to_string(),
+                description: "Fraction of the distance to the center that is blurred (0.0 to 1.0)".to_string(),
+            },
+            FilterParam {
+                name: "Center X".to_string(),
+                param_type: ParamType::Float { min: 0.0, max: 1.0 },
+                default: "0.5".to_string(),
+                description: "Horizontal center as a fraction of the width (0.0 to 1.0)".to_string(),
+            },
+            FilterParam {
+                name: "Center Y".to_string(),
+                param_type: ParamType::Float { min: 0.0, max: 1.0 },
+                default: "0.5".to_string(),
+                description: "Vertical center as a fraction of the height (0.0 to 1.0)".to_string(),
+            },
+            FilterParam {
+                name: "Linear Light".to_string(),
+                param_type: ParamType::Boolean,
+                default: "false".to_string(),
+                description: "Process in linear light instead of sRGB (true or false)".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "tilt-shift".to_string(),
+        description: "Miniature effect with a sharp focus band".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Position".to_string(),
+                param_type: ParamType::Float { min: 0.0, max: 1.0 },
+                default: "0.5".to_string(),
+                description: "Center of the focus band across the image, 0 at one edge and 1 at the other (0.0 to 1.0)".to_string(),
+            },
+            FilterParam {
+                name: "Angle".to_string(),
+                param_type: ParamType::Float { min: -90.0, max: 90.0 },
+                default: "0.0".to_string(),
+                description: "Tilt of the focus band in degrees (-90 to 90)".to_string(),
+            },
+            FilterParam {
+                name: "Band Width".to_string(),
+                param_type: ParamType::Float { min: 0.0, max: 1.0 },
+                default: "0.2".to_string(),
+                description: "Sharp band width as a fraction of the image across the band (0.0 to 1.0)".to_string(),
+            },
+            FilterParam {
+                name: "Falloff".to_string(),
+                param_type: ParamType::Float { min: 0.0, max: 1.0 },
+                default: "0.25".to_string(),
+                description: "Distance over which blur ramps up to full (0.0 to 1.0)".to_string(),
+            },
+            FilterParam {
+                name: "Max Sigma".to_string(),
+                param_type: ParamType::Float { min: 0.1, max: 20.0 },
+                default: "6.0".to_string(),
+                description: "Blur at full strength (0.1 to 20.0)".to_string(),
+            },
+            FilterParam {
+                name: "Linear Light".to_string(),
+                param_type: ParamType::Boolean,
+                default: "false".to_string(),
+                description: "Process in linear light instead of sRGB (true or false)".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "mask-blur".to_string(),
+        description: "Depth-of-field blur driven by a grayscale mask".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Mask File".to_string(),
+                param_type: ParamType::File,
+                default: "mask.png".to_string(),
+                description: "Grayscale image; white is fully blurred, black stays sharp".to_string(),
+            },
+            FilterParam {
+                name: "Max Sigma".to_string(),
+                param_type: ParamType::Float { min: 0.1, max: 20.0 },
+                default: "6.0".to_string(),
+                description: "Blur at full strength (0.1 to 20.0)".to_string(),
+            },
+            FilterParam {
+                name: "Invert Mask".to_string(),
+                param_type: ParamType::Boolean,
+                default: "false".to_string(),
+                description: "Blur the dark areas of the mask instead (true or false)".to_string(),
+            },
+            FilterParam {
+                name: "Linear Light".to_string(),
+                param_type: ParamType::Boolean,
+                default: "false".to_string(),
+                description: "Process in linear light instead of sRGB (true or false)".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "bilateral".to_string(),
+        description: "Edge-preserving smoothing by spatial and color distance".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Spatial Sigma".to_string(),
+                param_type: ParamType::Float { min: 0.5, max: 64.0 },
+                default: "4.0".to_string(),
+                description: "Spatial extent of the smoothing (0.5 to 64.0)".to_string(),
+            },
+            FilterParam {
+                name: "Range Sigma".to_string(),
+                param_type: ParamType::Float { min: 1.0, max: 255.0 },
+                default: "25.0".to_string(),
+                description: "Color difference still treated as the same surface (1 to 255)".to_string(),
+            },
+            FilterParam {
+                name: "Fast Approximation".to_string(),
+                param_type: ParamType::Boolean,
+                default: "false".to_string(),
+                description: "Use a bilateral grid, recommended for large sigmas".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "guided-filter".to_string(),
+        description: "Edge-preserving smoothing guided by the image's luma".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Radius".to_string(),
+                param_type: ParamType::Integer { min: 1, max: 200 },
+                default: "8".to_string(),
+                description: "Window radius in pixels (1 to 200)".to_string(),
+            },
+            FilterParam {
+                name: "Epsilon".to_string(),
+                param_type: ParamType::Float { min: 0.0001, max: 1.0 },
+                default: "0.01".to_string(),
+                description: "Regularization; larger values smooth stronger edges (0.0001 to 1.0)".to_string(),
+            },
+            FilterParam {
+                name: "Fast Approximation".to_string(),
+                param_type: ParamType::Boolean,
+                default: "false".to_string(),
+                description: "Fit on a subsampled image, recommended for large radii".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "median".to_string(),
+        description: "Median filter for salt-and-pepper and speckle noise".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Radius".to_string(),
+                param_type: ParamType::Integer { min: 1, max: 100 },
+                default: "2".to_string(),
+                description: "Window radius in pixels (1 to 100)".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "nl-means".to_string(),
+        description: "Non-local means denoising for high-ISO photos and scans".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Strength".to_string(),
+                param_type: ParamType::Float { min: 1.0, max: 100.0 },
+                default: "10.0".to_string(),
+                description: "Filtering strength, roughly the noise level (1 to 100)".to_string(),
+            },
+            FilterParam {
+                name: "Patch Radius".to_string(),
+                param_type: ParamType::Integer { min: 1, max: 10 },
+                default: "3".to_string(),
+                description: "Radius of the compared patches (1 to 10)".to_string(),
+            },
+            FilterParam {
+                name: "Search Radius".to_string(),
+                param_type: ParamType::Integer { min: 1, max: 20 },
+                default: "7".to_string(),
+                description: "How far to look for similar patches (1 to 20)".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "convolve".to_string(),
+        description: "Convolve with a custom kernel".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Kernel".to_string(),
+                param_type: ParamType::Text,
+                default: "0 -1 0; -1 5 -1; 0 -1 0".to_string(),
+                description: "Rows separated by ';', or a path to a kernel file".to_string(),
+            },
+            FilterParam {
+                name: "Divisor".to_string(),
+                param_type: ParamType::Float { min: -10000.0, max: 10000.0 },
+                default: "0".to_string(),
+                description: "Divide the result by this, 0 to use the kernel sum".to_string(),
+            },
+            FilterParam {
+                name: "Bias".to_string(),
+                param_type: ParamType::Float { min: -255.0, max: 255.0 },
+                default: "0".to_string(),
+                description: "Added after dividing (-255 to 255)".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "clamp".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "sharpen".to_string(),
+        description: "Sharpen the image".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Sharpen Strength".to_string(),
+                param_type: ParamType::Float { min: 0.1, max: 3.0 },
+                default: "1.0".to_string(),
+                description: "Sharpen strength (0.1 to 3.0)".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "unsharp-mask".to_string(),
+        description: "Gaussian unsharp mask with amount and threshold".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Radius".to_string(),
+                param_type: ParamType::Float { min: 0.1, max: 50.0 },
+                default: "2.0".to_string(),
+                description: "Gaussian sigma of the mask (0.1 to 50.0)".to_string(),
+            },
+            FilterParam {
+                name: "Amount".to_string(),
+                param_type: ParamType::Float { min: 0.0, max: 5.0 },
+                default: "1.0".to_string(),
+                description: "How strongly detail is boosted (0.0 to 5.0)".to_string(),
+            },
+            FilterParam {
+                name: "Threshold".to_string(),
+                param_type: ParamType::Integer { min: 0, max: 255 },
+                default: "3".to_string(),
+                description: "Minimum difference before sharpening applies (0 to 255)".to_string(),
+            },
+            FilterParam {
+                name: "Luminance Only".to_string(),
+                param_type: ParamType::Boolean,
+                default: "true".to_string(),
+                description: "Sharpen luma only to avoid color fringing".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "edge-detection".to_string(),
+        description: "Detect edges with Sobel, Scharr, Prewitt, LoG or Canny".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Operator".to_string(),
+                param_type: ParamType::Choice {
+                    options: vec!["sobel".to_string(), "scharr".to_string(), "prewitt".to_string(), "log".to_string(), "canny".to_string()],
+                },
+                default: "sobel".to_string(),
+                description: "Edge operator".to_string(),
+            },
+            FilterParam {
+                name: "Smoothing Sigma".to_string(),
+                param_type: ParamType::Float { min: 0.1, max: 10.0 },
+                default: "1.4".to_string(),
+                description: "Gaussian pre-smoothing for LoG and Canny (0.1 to 10.0)".to_string(),
+            },
+            FilterParam {
+                name: "Low Threshold".to_string(),
+                param_type: ParamType::Float { min: 0.0, max: 1000.0 },
+                default: "20".to_string(),
+                description: "Canny weak edge threshold, or LoG zero-crossing slope".to_string(),
+            },
+            FilterParam {
+                name: "High Threshold".to_string(),
+                param_type: ParamType::Float { min: 0.0, max: 1000.0 },
+                default: "60".to_string(),
+                description: "Canny strong edge threshold".to_string(),
+            },
+            FilterParam {
+                name: "Output".to_string(),
+                param_type: ParamType::Choice {
+                    options: vec!["magnitude".to_string(), "direction".to_string(), "overlay".to_string()],
+                },
+                default: "magnitude".to_string(),
+                description: "Gray edges, hue-coded gradient direction, or edges drawn over the image".to_string(),
+            },
+            FilterParam {
+                name: "Overlay Color".to_string(),
+                param_type: ParamType::Color,
+                default: "#ff3030".to_string(),
+                description: "Edge color used by the overlay output".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "thresholding".to_string(),
+        description: "Apply binary thresholding".to_string(),
+        requires_param: true,
+        params: vec![FilterParam {
+            name: "Threshold Value".to_string(),
+            param_type: ParamType::Integer { min: 0, max: 255 },
+            default: "128".to_string(),
+            description: "Threshold value (0 to 255)".to_string(),
+        }],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "adaptive-threshold".to_string(),
+        description: "Otsu or local mean/Gaussian/Sauvola binarization".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Method".to_string(),
+                param_type: ParamType::Choice {
+                    options: vec!["otsu".to_string(), "mean".to_string(), "gaussian".to_string(), "sauvola".to_string()],
+                },
+                default: "sauvola".to_string(),
+                description: "Global Otsu threshold or a local method".to_string(),
+            },
+            FilterParam {
+                name: "Window".to_string(),
+                param_type: ParamType::Integer { min: 3, max: 501 },
+                default: "31".to_string(),
+                description: "Local window size in pixels (3 to 501)".to_string(),
+            },
+            FilterParam {
+                name: "Offset".to_string(),
+                param_type: ParamType::Float { min: -255.0, max: 255.0 },
+                default: "5".to_string(),
+                description: "Subtracted from the local threshold (-255 to 255)".to_string(),
+            },
+            FilterParam {
+                name: "Sauvola K".to_string(),
+                param_type: ParamType::Float { min: 0.0, max: 1.0 },
+                default: "0.2".to_string(),
+                description: "Contrast sensitivity for Sauvola (0.0 to 1.0)".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "dither".to_string(),
+        description: "Dithered grayscale or 1-bit black and white".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Method".to_string(),
+                param_type: ParamType::Choice {
+                    options: dither_options(),
+                },
+                default: "floyd-steinberg".to_string(),
+                description: "Error diffusion, ordered Bayer or blue-noise dithering".to_string(),
+            },
+            FilterParam {
+                name: "Gray Levels".to_string(),
+                param_type: ParamType::Integer { min: 2, max: 256 },
+                default: "2".to_string(),
+                description: "Number of gray levels, 2 for 1-bit output".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "morphology".to_string(),
+        description: "Erode, dilate, open, close, top-hat or gradient".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Operation".to_string(),
+                param_type: ParamType::Choice {
+                    options: vec![
+                        "erode".to_string(),
+                        "dilate".to_string(),
+                        "open".to_string(),
+                        "close".to_string(),
+                        "top-hat".to_string(),
+                        "black-hat".to_string(),
+                        "gradient".to_string(),
+                    ],
+                },
+                default: "open".to_string(),
+                description: "Morphological operation".to_string(),
+            },
+            FilterParam {
+                name: "Element".to_string(),
+                param_type: ParamType::Choice {
+                    options: vec!["square".to_string(), "cross".to_string(), "disk".to_string()],
+                },
+                default: "square".to_string(),
+                description: "Shape of the structuring element".to_string(),
+            },
+            FilterParam {
+                name: "Radius".to_string(),
+                param_type: ParamType::Integer { min: 1, max: 100 },
+                default: "2".to_string(),
+                description: "Element radius in pixels (1 to 100)".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "histogram-equalization".to_string(),
+        description: "Spread luminance evenly across the tonal range".to_string(),
+        requires_param: false,
+        params: vec![],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "clahe".to_string(),
+        description: "Contrast limited adaptive histogram equalization".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Grid Size".to_string(),
+                param_type: ParamType::Integer { min: 1, max: 64 },
+                default: "8".to_string(),
+                description: "Number of tiles per side (1 to 64)".to_string(),
+            },
+            FilterParam {
+                name: "Clip Limit".to_string(),
+                param_type: ParamType::Float { min: 1.0, max: 40.0 },
+                default: "2.0".to_string(),
+                description: "Histogram clip limit, higher allows more contrast (1.0 to 40.0)".to_string(),
+            },
+        ],
+        category: FilterCategory::Enhancement,
+        icon: "",
+      },
+      Filter {
+        name: "sepia".to_string(),
+        description: "Apply sepia filter".to_string(),
+        requires_param: false,
+        params: vec![],
+        category: FilterCategory::Artistic,
+        icon: "",
+      },
+      Filter {
+        name: "gradient-map".to_string(),
+        description: "Map luminance onto a multi-stop color gradient".to_string(),
+        requires_param: true,
+        params: vec![FilterParam {
+            name: "Gradient Stops".to_string(),
+            param_type: ParamType::Text,
+            default: "#1a1040@0,#d0406a@0.55,#ffe8a0@1".to_string(),
+            description: "Comma separated #rrggbb@position stops, positions 0 to 1".to_string(),
+        }],
+        category: FilterCategory::Artistic,
+        icon: "",
+      },
+      Filter {
+        name: "duotone".to_string(),
+        description: "Two-color treatment from shadows to highlights".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Shadow Color".to_string(),
+                param_type: ParamType::Color,
+                default: "#1d2b53".to_string(),
+                description: "Color for the darkest tones".to_string(),
+            },
+            FilterParam {
+                name: "Highlight Color".to_string(),
+                param_type: ParamType::Color,
+                default: "#ffcc66".to_string(),
+                description: "Color for the brightest tones".to_string(),
+            },
+        ],
+        category: FilterCategory::Artistic,
+        icon: "",
+      },
+      Filter {
+        name: "tritone".to_string(),
+        description: "Three-color treatment with a midtone color".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Shadow Color".to_string(),
+                param_type: ParamType::Color,
+                default: "#101820".to_string(),
+                description: "Color for the darkest tones".to_string(),
+            },
+            FilterParam {
+                name: "Midtone Color".to_string(),
+                param_type: ParamType::Color,
+                default: "#a05a2c".to_string(),
+                description: "Color for the middle tones".to_string(),
+            },
+            FilterParam {
+                name: "Highlight Color".to_string(),
+                param_type: ParamType::Color,
+                default: "#f5e6c8".to_string(),
+                description: "Color for the brightest tones".to_string(),
+            },
+        ],
+        category: FilterCategory::Artistic,
+        icon: "",
+      },
+      Filter {
+        name: "vignette".to_string(),
+        description: "Apply vignette effect".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Vignette Strength".to_string(),
+                param_type: ParamType::Float { min: 0.1, max: 1.0 },
+                default: "0.5".to_string(),
+                description: "Vignette strength (0.1 to 1.0)".to_string(),
+            },
+            FilterParam {
+                name: "Linear Light".to_string(),
+                param_type: ParamType::Boolean,
+                default: "false".to_string(),
+                description: "Process in linear light instead of sRGB (true or false)".to_string(),
+            },
+        ],
+        category: FilterCategory::Artistic,
+        icon: "",
+      },
+      Filter {
+        name: "noise".to_string(),
+        description: "Add noise to image".to_string(),
+        requires_param: true,
+        params: vec![FilterParam {
+            name: "Noise Strength".to_string(),
+            param_type: ParamType::Integer { min: 1, max: 100 },
+            default: "20".to_string(),
+            description: "Noise strength (1 to 100)".to_string(),
+        }],
+        category: FilterCategory::Artistic,
+        icon: "",
+      },
+      Filter {
+        name: "pixelate".to_string(),
+        description: "Mosaic blocks over the image or a rectangle".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Block Size".to_string(),
+                param_type: ParamType::Integer { min: 2, max: 512 },
+                default: "16".to_string(),
+                description: "Block edge length in pixels (2 to 512)".to_string(),
+            },
+            FilterParam {
+                name: "Mode".to_string(),
+                param_type: ParamType::Choice {
+                    options: vec!["mean".to_string(), "center".to_string(), "dominant".to_string()],
+                },
+                default: "mean".to_string(),
+                description: "Average, center sample or most common color per block".to_string(),
+            },
+            FilterParam {
+                name: "X Position".to_string(),
+                param_type: ParamType::Integer { min: 0, max: 10000 },
+                default: "0".to_string(),
+                description: "X coordinate of the region start".to_string(),
+            },
+            FilterParam {
+                name: "Y Position".to_string(),
+                param_type: ParamType::Integer { min: 0, max: 10000 },
+                default: "0".to_string(),
+                description: "Y coordinate of the region start".to_string(),
+            },
+            FilterParam {
+                name: "Width".to_string(),
+                param_type: ParamType::Integer { min: 0, max: 10000 },
+                default: "0".to_string(),
+                description: "Region width, 0 to reach the right edge".to_string(),
+            },
+            FilterParam {
+                name: "Height".to_string(),
+                param_type: ParamType::Integer { min: 0, max: 10000 },
+                default: "0".to_string(),
+                description: "Region height, 0 to reach the bottom edge".to_string(),
+            },
+        ],
+        category: FilterCategory::Artistic,
+        icon: "",
+      },
+      Filter {
+        name: "oil".to_string(),
+        description: "Apply oil painting effect".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Radius".to_string(),
+                param_type: ParamType::Integer { min: 1, max: 10 },
+                default: "4".to_string(),
+                description: "Oil painting radius (1 to 10)".to_string(),
+            },
+            FilterParam {
+                name: "Intensity Levels".to_string(),
+                param_type: ParamType::Integer { min: 5, max: 50 },
+                default: "20".to_string(),
+                description: "Oil painting intensity levels (5 to 50)".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Artistic,
+        icon: "",
+      },
+      Filter {
+        name: "emboss".to_string(),
+        description: "Emboss or relief lit from a chosen direction".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Light Angle".to_string(),
+                param_type: ParamType::Float { min: 0.0, max: 360.0 },
+                default: "135.0".to_string(),
+                description: "Direction the light comes from in degrees (0 is from the right)".to_string(),
+            },
+            FilterParam {
+                name: "Elevation".to_string(),
+                param_type: ParamType::Float { min: 1.0, max: 90.0 },
+                default: "45.0".to_string(),
+                description: "Light height above the surface in degrees (1 to 90)".to_string(),
+            },
+            FilterParam {
+                name: "Depth".to_string(),
+                param_type: ParamType::Float { min: 1.0, max: 100.0 },
+                default: "10.0".to_string(),
+                description: "Height of the relief in pixels (1 to 100)".to_string(),
+            },
+            FilterParam {
+                name: "Output".to_string(),
+                param_type: ParamType::Choice {
+                    options: vec!["relief".to_string(), "color".to_string()],
+                },
+                default: "relief".to_string(),
+                description: "Gray relief or the relief overlaid on the original colors".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Artistic,
+        icon: "",
+      },
+      Filter {
+        name: "cartoon".to_string(),
+        description: "Flat posterized colors with bold outlines".to_string(),
         requires_param: true,
-        params: vec![FilterParam {
-          name: "factor".to_string(),
-          param_type: ParamType::Float { min: 0.1, max: 3.0 },
-          default: "1.5".to_string(),
-          description: "Contrast factor (0.1 to 3.0)".to_string(),
-        }],
-        category: FilterCategory::Enhancement,
+        params: vec![
+            FilterParam {
+                name: "Smoothing".to_string(),
+                param_type: ParamType::Float { min: 10.0, max: 100.0 },
+                default: "40.0".to_string(),
+                description: "How strongly colors are flattened before posterizing (10 to 100)".to_string(),
+            },
+            FilterParam {
+                name: "Color Levels".to_string(),
+                param_type: ParamType::Integer { min: 2, max: 16 },
+                default: "6".to_string(),
+                description: "Levels per channel (2 to 16)".to_string(),
+            },
+            FilterParam {
+                name: "Edge Threshold".to_string(),
+                param_type: ParamType::Float { min: 5.0, max: 255.0 },
+                default: "40.0".to_string(),
+                description: "Edge strength needed for an outline (5 to 255)".to_string(),
+            },
+            FilterParam {
+                name: "Edge Thickness".to_string(),
+                param_type: ParamType::Integer { min: 1, max: 8 },
+                default: "2".to_string(),
+                description: "Outline thickness, 1 is a single pixel (1 to 8)".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Artistic,
         icon: "",
       },
       Filter {
-        name: "gaussian-blur".to_string(),
-        description: "Apply Gaussian blur".to_string(),
+        name: "pencil-sketch".to_string(),
+        description: "Grayscale pencil drawing".to_string(),
         requires_param: true,
-        params: vec![FilterParam {
-            name: "Blur Sigma".to_string(),
-            param_type: ParamType::Float { min: 0.1, max: 20.0 },
-            default: "2.0".to_string(),
-            description: "Blur intensity (0.1 to 20.0)".to_string(),
-        }],
-        category: FilterCategory::Enhancement,
+        params: vec![
+            FilterParam {
+                name: "Stroke Width".to_string(),
+                param_type: ParamType::Float { min: 0.5, max: 30.0 },
+                default: "6.0".to_string(),
+                description: "Blur sigma that sets how wide the pencil lines are (0.5 to 30.0)".to_string(),
+            },
+            FilterParam {
+                name: "Shading".to_string(),
+                param_type: ParamType::Float { min: 0.0, max: 1.0 },
+                default: "0.3".to_string(),
+                description: "How much of the original tone is shaded in (0.0 to 1.0)".to_string(),
+            },
+            FilterParam {
+                name: "Border".to_string(),
+                param_type: ParamType::Border,
+                default: "mirror".to_string(),
+                description: "How pixels outside the image are read".to_string(),
+            },
+        ],
+        category: FilterCategory::Artistic,
         icon: "",
       },
       Filter {
-        name: "box-blur".to_string(),
-        description: "Apply box blur".to_string(),
+        name: "halftone".to_string(),
+        description: "Print-style halftone screen".to_string(),
         requires_param: true,
-        params: vec![FilterParam {
-          name: "Blur Radius".to_string(),
-          param_type: ParamType::Integer { min: 1, max: 50 },
-          default: "5".to_string(),
-          description: "Blur radius (1 to 50)".to_string(),
-        }],
-        category: FilterCategory::Enhancement,
+        params: vec![
+            FilterParam {
+                name: "Cell Size".to_string(),
+                param_type: ParamType::Float { min: 2.0, max: 50.0 },
+                default: "8.0".to_string(),
+                description: "Screen cell size in pixels (2 to 50)".to_string(),
+            },
+            FilterParam {
+                name: "Inks".to_string(),
+                param_type: ParamType::Choice {
+                    options: vec!["cmyk".to_string(), "single".to_string()],
+                },
+                default: "cmyk".to_string(),
+                description: "Four CMYK screens or a single ink on white".to_string(),
+            },
+            FilterParam {
+                name: "Screen Angles".to_string(),
+                param_type: ParamType::Text,
+                default: "15,75,0,45".to_string(),
+                description: "Degrees for cyan, magenta, yellow, black; single ink uses the first".to_string(),
+            },
+            FilterParam {
+                name: "Dot Shape".to_string(),
+                param_type: ParamType::Choice {
+                    options: vec!["round".to_string(), "line".to_string(), "square".to_string()],
+                },
+                default: "round".to_string(),
+                description: "Shape of the halftone dots".to_string(),
+            },
+            FilterParam {
+                name: "Ink Color".to_string(),
+                param_type: ParamType::Color,
+                default: "#000000".to_string(),
+                description: "Ink color for single-ink mode (#rrggbb)".to_string(),
+            },
+        ],
+        category: FilterCategory::Artistic,
         icon: "",
       },
       Filter {
-        name: "sharpen".to_string(),
-        description: "Sharpen the image".to_string(),
+        name: "saturate".to_string(),
+        description: "Adjust color saturation".to_string(),
         requires_param: true,
-        params: vec![FilterParam {
-            name: "Sharpen Strength".to_string(),
-            param_type: ParamType::Float { min: 0.1, max: 3.0 },
-            default: "1.0".to_string(),
-            description: "Sharpen strength (0.1 to 3.0)".to_string(),
-        }],
-        category: FilterCategory::Enhancement,
+        params: vec![
+            FilterParam {
+                name: "Saturation Factor".to_string(),
+                param_type: ParamType::Float { min: 0.0, max: 3.0 },
+                default: "1.5".to_string(),
+                description: "Saturation factor (0.0 to 3.0)".to_string(),
+            },
+            FilterParam {
+                name: "Color Space".to_string(),
+                param_type: ParamType::Choice {
+                    options: vec!["hsl".to_string(), "oklch".to_string(), "lch".to_string()],
+                },
+                default: "hsl".to_string(),
+                description: "Space to work in; oklch and lch keep perceived lightness".to_string(),
+            },
+        ],
+        category: FilterCategory::Color,
         icon: "",
       },
       Filter {
-        name: "edge-detection".to_string(),
-        description: "Apply Sobel edge detection".to_string(),
-        requires_param: false,
-        params: vec![],
-        category: FilterCategory::Enhancement,
+        name: "vibrance".to_string(),
+        description: "Boost muted colors more than already saturated ones".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Vibrance Amount".to_string(),
+                param_type: ParamType::Float { min: -100.0, max: 100.0 },
+                default: "40.0".to_string(),
+                description: "Vibrance strength (-100 to 100)".to_string(),
+            },
+            FilterParam {
+                name: "Protect Skin".to_string(),
+                param_type: ParamType::Boolean,
+                default: "true".to_string(),
+                description: "Leave skin-tone hues mostly untouched (true or false)".to_string(),
+            },
+        ],
+        category: FilterCategory::Color,
         icon: "",
       },
       Filter {
-        name: "thresholding".to_string(),
-        description: "Apply binary thresholding".to_string(),
+        name: "channel-mixer".to_string(),
+        description: "Mix output channels from input channels with a 3x3 matrix".to_string(),
         requires_param: true,
         params: vec![FilterParam {
-            name: "Threshold Value".to_string(),
-            param_type: ParamType::Integer { min: 0, max: 255 },
-            default: "128".to_string(),
-            description: "Threshold value (0 to 255)".to_string(),
+            name: "Matrix".to_string(),
+            param_type: ParamType::Text,
+            default: "red-filter-bw".to_string(),
+            description: "Preset (identity, sepia, luma-bw, red/green/blue-filter-bw) or 9 values, or 12 with offsets".to_string(),
         }],
-        category: FilterCategory::Enhancement,
-        icon: "",
-      },
-      Filter {
-        name: "sepia".to_string(),
-        description: "Apply sepia filter".to_string(),
-        requires_param: false,
-        params: vec![],
-        category: FilterCategory::Artistic,
+        category: FilterCategory::Color,
         icon: "",
       },
       Filter {
-        name: "vignette".to_string(),
-        description: "Apply vignette effect".to_string(),
+        name: "channel-adjust".to_string(),
+        description: "Per-channel brightness, contrast and gamma".to_string(),
         requires_param: true,
-        params: vec![FilterParam {
-            name: "Vignette Strength".to_string(),
-            param_type: ParamType::Float { min: 0.1, max: 1.0 },
-            default: "0.5".to_string(),
-            description: "Vignette strength (0.1 to 1.0)".to_string(),
-        }],
-        category: FilterCategory::Artistic,
+        params: vec![
+            FilterParam {
+                name: "Red Brightness".to_string(),
+                param_type: ParamType::Integer { min: -100, max: 100 },
+                default: "0".to_string(),
+                description: "Red channel brightness (-100 to 100)".to_string(),
+            },
+            FilterParam {
+                name: "Green Brightness".to_string(),
+                param_type: ParamType::Integer { min: -100, max: 100 },
+                default: "0".to_string(),
+                description: "Green channel brightness (-100 to 100)".to_string(),
+            },
+            FilterParam {
+                name: "Blue Brightness".to_string(),
+                param_type: ParamType::Integer { min: -100, max: 100 },
+                default: "0".to_string(),
+                description: "Blue channel brightness (-100 to 100)".to_string(),
+            },
+            FilterParam {
+                name: "Red Contrast".to_string(),
+                param_type: ParamType::Float { min: 0.1, max: 3.0 },
+                default: "1.0".to_string(),
+                description: "Red channel contrast (0.1 to 3.0)".to_string(),
+            },
+            FilterParam {
+                name: "Green Contrast".to_string(),
+                param_type: ParamType::Float { min: 0.1, max: 3.0 },
+                default: "1.0".to_string(),
+                description: "Green channel contrast (0.1 to 3.0)".to_string(),
+            },
+            FilterParam {
+                name: "Blue Contrast".to_string(),
+                param_type: ParamType::Float { min: 0.1, max: 3.0 },
+                default: "1.0".to_string(),
+                description: "Blue channel contrast (0.1 to 3.0)".to_string(),
+            },
+            FilterParam {
+                name: "Red Gamma".to_string(),
+                param_type: ParamType::Float { min: 0.1, max: 10.0 },
+                default: "1.0".to_string(),
+                description: "Red channel gamma, above 1.0 brightens (0.1 to 10.0)".to_string(),
+            },
+            FilterParam {
+                name: "Green Gamma".to_string(),
+                param_type: ParamType::Float { min: 0.1, max: 10.0 },
+                default: "1.0".to_string(),
+                description: "Green channel gamma, above 1.0 brightens (0.1 to 10.0)".to_string(),
+            },
+            FilterParam {
+                name: "Blue Gamma".to_string(),
+                param_type: ParamType::Float { min: 0.1, max: 10.0 },
+                default: "1.0".to_string(),
+                description: "Blue channel gamma, above 1.0 brightens (0.1 to 10.0)".to_string(),
+            },
+        ],
+        category: FilterCategory::Color,
         icon: "",
       },
       Filter {
-        name: "noise".to_string(),
-        description: "Add noise to image".to_string(),
+        name: "cube-lut".to_string(),
+        description: "Apply a 1D or 3D .cube LUT".to_string(),
         requires_param: true,
-        params: vec![FilterParam {
-            name: "Noise Strength".to_string(),
-            param_type: ParamType::Integer { min: 1, max: 100 },
-            default: "20".to_string(),
-            description: "Noise strength (1 to 100)".to_string(),
-        }],
-        category: FilterCategory::Artistic,
+        params: vec![
+            FilterParam {
+                name: "Cube File".to_string(),
+                param_type: ParamType::File,
+                default: "look.cube".to_string(),
+                description: "Path to an Adobe/Resolve .cube file".to_string(),
+            },
+            FilterParam {
+                name: "Interpolation".to_string(),
+                param_type: ParamType::Choice {
+                    options: vec!["tetrahedral".to_string(), "trilinear".to_string()],
+                },
+                default: "tetrahedral".to_string(),
+                description: "How 3D LUT entries are blended".to_string(),
+            },
+        ],
+        category: FilterCategory::Color,
         icon: "",
       },
       Filter {
-        name: "oil".to_string(),
-        description: "Apply oil painting effect".to_string(),
+        name: "quantize".to_string(),
+        description: "Reduce the image to a limited palette".to_string(),
         requires_param: true,
         params: vec![
             FilterParam {
-                name: "Radius".to_string(),
-                param_type: ParamType::Integer { min: 1, max: 10 },
-                default: "4".to_string(),
-                description: "Oil painting radius (1 to 10)".to_string(),
+                name: "Colors".to_string(),
+                param_type: ParamType::Integer { min: 2, max: 256 },
+                default: "16".to_string(),
+                description: "Number of palette colors (2 to 256)".to_string(),
             },
             FilterParam {
-                name: "Intensity Levels".to_string(),
-                param_type: ParamType::Integer { min: 5, max: 50 },
-                default: "20".to_string(),
-                description: "Oil painting intensity levels (5 to 50)".to_string(),
+                name: "Method".to_string(),
+                param_type: ParamType::Choice {
+                    options: vec!["median-cut".to_string(), "octree".to_string(), "k-means".to_string()],
+                },
+                default: "median-cut".to_string(),
+                description: "How the palette is chosen".to_string(),
+            },
+            FilterParam {
+                name: "Dither".to_string(),
+                param_type: ParamType::Choice {
+                    options: dither_options(),
+                },
+                default: "none".to_string(),
+                description: "Dithering used when snapping to the palette".to_string(),
+            },
+            FilterParam {
+                name: "Indexed PNG Path".to_string(),
+                param_type: ParamType::Text,
+                default: "none".to_string(),
+                description: "Also write an indexed PNG here, or 'none'".to_string(),
             },
         ],
-        category: FilterCategory::Artistic,
+        category: FilterCategory::Color,
         icon: "",
       },
       Filter {
-        name: "saturate".to_string(),
-        description: "Adjust color saturation".to_string(),
+        name: "palette-map".to_string(),
+        description: "Map colors onto a palette file (.gpl or hex list)".to_string(),
         requires_param: true,
-        params: vec![FilterParam {
-            name: "Saturation Factor".to_string(),
-            param_type: ParamType::Float { min: 0.0, max: 3.0 },
-            default: "1.5".to_string(),
-            description: "Saturation factor (0.0 to 3.0)".to_string(),
-        }],
+        params: vec![
+            FilterParam {
+                name: "Palette File".to_string(),
+                param_type: ParamType::File,
+                default: "palette.gpl".to_string(),
+                description: "GIMP palette or one #rrggbb color per line".to_string(),
+            },
+            FilterParam {
+                name: "Dither".to_string(),
+                param_type: ParamType::Choice {
+                    options: dither_options(),
+                },
+                default: "none".to_string(),
+                description: "Dithering used when snapping to the palette".to_string(),
+            },
+            FilterParam {
+                name: "Indexed PNG Path".to_string(),
+                param_type: ParamType::Text,
+                default: "none".to_string(),
+                description: "Also write an indexed PNG here, or 'none'".to_string(),
+            },
+        ],
         category: FilterCategory::Color,
         icon: "",
       },
@@ -297,12 +1482,86 @@ impl App {
         name: "hue-rotate".to_string(),
         description: "Rotate hue colors".to_string(),
         requires_param: true,
-        params: vec![FilterParam {
-            name: "Hue Rotation".to_string(),
-            param_type: ParamType::Float { min: -360.0, max: 360.0 },
-            default: "90.0".to_string(),
-            description: "Hue rotation in degrees (-360 to 360)".to_string(),
-        }],
+        params: vec![
+            FilterParam {
+                name: "Hue Rotation".to_string(),
+                param_type: ParamType::Float { min: -360.0, max: 360.0 },
+                default: "90.0".to_string(),
+                description: "Hue rotation in degrees (-360 to 360)".to_string(),
+            },
+            FilterParam {
+                name: "Color Space".to_string(),
+                param_type: ParamType::Choice {
+                    options: vec!["hsl".to_string(), "oklch".to_string(), "lch".to_string()],
+                },
+                default: "hsl".to_string(),
+                description: "Space to work in; oklch and lch keep perceived lightness".to_string(),
+            },
+        ],
+        category: FilterCategory::Color,
+        icon: "",
+      },
+      Filter {
+        name: "lightness".to_string(),
+        description: "Shift lightness without changing hue or chroma".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Lightness Shift".to_string(),
+                param_type: ParamType::Float { min: -100.0, max: 100.0 },
+                default: "10.0".to_string(),
+                description: "Lightness change in percent (-100 to 100)".to_string(),
+            },
+            FilterParam {
+                name: "Color Space".to_string(),
+                param_type: ParamType::Choice {
+                    options: vec!["hsl".to_string(), "oklch".to_string(), "lch".to_string()],
+                },
+                default: "oklch".to_string(),
+                description: "Space to work in; oklch and lch keep perceived lightness".to_string(),
+            },
+        ],
+        category: FilterCategory::Color,
+        icon: "",
+      },
+      Filter {
+        name: "white-balance".to_string(),
+        description: "Correct color casts with temperature/tint or automatic modes".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Mode".to_string(),
+                param_type: ParamType::Choice {
+                    options: vec!["manual".to_string(), "gray-world".to_string(), "white-patch".to_string(), "gray-point".to_string()],
+                },
+                default: "gray-world".to_string(),
+                description: "How the correction is chosen".to_string(),
+            },
+            FilterParam {
+                name: "Temperature".to_string(),
+                param_type: ParamType::Integer { min: 2000, max: 12000 },
+                default: "6500".to_string(),
+                description: "Scene light in Kelvin, manual mode only (2000 to 12000)".to_string(),
+            },
+            FilterParam {
+                name: "Tint".to_string(),
+                param_type: ParamType::Float { min: -100.0, max: 100.0 },
+                default: "0.0".to_string(),
+                description: "Green (-) to magenta (+), manual mode only (-100 to 100)".to_string(),
+            },
+            FilterParam {
+                name: "Gray Point X".to_string(),
+                param_type: ParamType::Integer { min: 0, max: 10000 },
+                default: "0".to_string(),
+                description: "X of a neutral pixel, gray-point mode only".to_string(),
+            },
+            FilterParam {
+                name: "Gray Point Y".to_string(),
+                param_type: ParamType::Integer { min: 0, max: 10000 },
+                default: "0".to_string(),
+                description: "Y of a neutral pixel, gray-point mode only".to_string(),
+            },
+        ],
         category: FilterCategory::Color,
         icon: "",
       },
@@ -379,6 +1638,33 @@ impl App {
         category: FilterCategory::Utility,
         icon: "",
       },
+      Filter {
+        name: "export-cube".to_string(),
+        description: "Bake a pipeline of color filters into a .cube LUT".to_string(),
+        requires_param: true,
+        params: vec![
+            FilterParam {
+                name: "Pipeline".to_string(),
+                param_type: ParamType::Text,
+                default: "saturate 1.2 oklch | contrast 1.1 false".to_string(),
+                description: "Filters with their parameters, separated by '|'".to_string(),
+            },
+            FilterParam {
+                name: "LUT Size".to_string(),
+                param_type: ParamType::Integer { min: 2, max: 65 },
+                default: "33".to_string(),
+                description: "Lattice points per axis (2 to 65)".to_string(),
+            },
+            FilterParam {
+                name: "Cube Path".to_string(),
+                param_type: ParamType::Text,
+                default: "look.cube".to_string(),
+                description: "Where to write the .cube file; the image output shows the baked look".to_string(),
+            },
+        ],
+        category: FilterCategory::Utility,
+        icon: "",
+      },
       // Filter {
       //   name: "ascii".to_string(),
       //   description: "Convert to ASCII art".to_string(),
@@ -408,6 +1694,8 @@ impl App {
       image_preview: None,
       has_image_support: Self::detect_image_support(),
       progress_receiver: None,
+      result_receiver: None,
+      linear_light: false,
     };
 
     app.filter_list_state.select(Some(0));
@@ -509,9 +1797,48 @@ impl App {
                     _ => Err("Value must be 'true' or 'false'".into()),
                 }
             }
+            ParamType::Text => {
+                if value.trim().is_empty() {
+                    Err("Value cannot be empty".into())
+                } else {
+                    self.validate_format(value, param)
+                }
+            }
+            ParamType::Choice { options } => {
+                if options.iter().any(|option| option == value) {
+                    Ok(())
+                } else {
+                    Err(format!("Value must be one of: {}", options.join(", ")).into())
+                }
+            }
+            ParamType::File => {
+                if std::path::Path::new(value).is_file() {
+                    self.validate_format(value, param)
+                } else {
+                    Err("File does not exist".into())
+                }
+            }
+            ParamType::Color => color::parse_hex_color(value).map(|_| ()),
+            ParamType::Border => value.parse::<BorderMode>().map(|_| ()).map_err(|error| error.into()),
         }
   }
 
+  // Parses values whose format is specific to a filter, so mistakes are caught while typing
+  // rather than on the worker thread.
+  fn validate_format(&self, value: &str, param: &FilterParam) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let filter_name = self.selected_filter.as_ref().map(|filter| filter.name.as_str()).unwrap_or_default();
+    match (filter_name, param.name.as_str()) {
+      ("curves", _) => enhancement::parse_curve_points(value).map(|_| ()),
+      ("convolve", "Kernel") => enhancement::Kernel::from_spec(value).map(|_| ()),
+      ("gradient-map", "Gradient Stops") => artistic::parse_gradient_stops(value).map(|_| ()),
+      ("channel-mixer", "Matrix") => value.parse::<color::ChannelMatrix>().map(|_| ()).map_err(|error| error.into()),
+      ("cube-lut", "Cube File") => lut::CubeLut::load(value).map(|_| ()),
+      // A two-point lattice runs every stage once, which is enough to catch bad names and parameters
+      ("export-cube", "Pipeline") => lut::bake_pipeline(value, 2, false).map(|_| ()),
+      _ => Ok(()),
+    }
+  }
+
   pub fn next_parameter(&mut self) {
         if let Some(filter) = &self.selected_filter {
             if self.current_param_index < filter.params.len() {
@@ -535,6 +1862,23 @@ impl App {
         }
   }
 
+  // Steps a Choice parameter through its options; other parameter types are left alone.
+  pub fn cycle_choice(&mut self, forward: bool) {
+    let Some(filter) = &self.selected_filter else {
+      return;
+    };
+    if let ParamType::Choice { options } = &filter.params[self.current_param_index].param_type {
+      let current = options.iter().position(|option| option.eq_ignore_ascii_case(self.current_input.trim()));
+      let next = match (current, forward) {
+        (Some(index), true) => (index + 1) % options.len(),
+        (Some(index), false) => (index + options.len() - 1) % options.len(),
+        (None, true) => 0,
+        (None, false) => options.len() - 1,
+      };
+      self.current_input = options[next].clone();
+    }
+  }
+
   pub fn previous_parameter(&mut self) {
     if self.current_param_index > 0 {
       self.param_values[self.current_param_index] = self.current_input.clone();
@@ -585,14 +1929,18 @@ impl App {
       let output_file = self.output_file.clone();
       let param_values = self.param_values.clone();
       let filter_name = filter.name.clone();
+      let linear_light = self.linear_light;
 
       let (progress_tx, progress_rx) = mpsc::channel();
+      let (result_tx, result_rx) = mpsc::channel();
 
       let _handle = thread::spawn(move || {
-        let _ = process_filter(&filter_name, &input_file, &output_file, &param_values, Some(progress_tx)); 
+        let result = process_filter(&filter_name, &input_file, &output_file, &param_values, linear_light, Some(progress_tx));
+        let _ = result_tx.send(result.map_err(|error| error.to_string()));
       });
 
       self.progress_receiver = Some(progress_rx);
+      self.result_receiver = Some(result_rx);
     }
   }
 
@@ -600,26 +1948,28 @@ impl App {
 
   pub fn update_progress(&mut self) -> bool {
     if let Some(ref receiver) = self.progress_receiver {
-        match receiver.try_recv() {
-            Ok(progress) => {
-                self.processing_progress = progress;
-                if progress >= 1.0 {
-                    self.message = if let Some(filter) = &self.selected_filter {
-                        format!("^_^ Successfully applied {} filter!\n\nOutput saved to: {}\n\nPress 'v' to view image or 'r' to process another", filter.name, self.output_file)
-                    } else {
-                        "Processing Completed!".to_string()
-                    };
-                    self.state = AppState::Result;
-                    self.progress_receiver = None;
-                    return true;
-                }
-                false
-            }
-            Err(_) => false,
+        while let Ok(progress) = receiver.try_recv() {
+            self.processing_progress = progress;
         }
-    } else {
-        false
     }
+
+    // The worker's result, not the last progress value, decides when processing is over
+    let outcome = match self.result_receiver.as_ref().map(|receiver| receiver.try_recv()) {
+        Some(Ok(outcome)) => outcome,
+        Some(Err(mpsc::TryRecvError::Disconnected)) => Err("Processing stopped unexpectedly".to_string()),
+        Some(Err(mpsc::TryRecvError::Empty)) | None => return false,
+    };
+
+    let filter_name = self.selected_filter.as_ref().map(|filter| filter.name.clone()).unwrap_or_default();
+    self.message = match outcome {
+        Ok(()) => format!("^_^ Successfully applied {} filter!\n\nOutput saved to: {}\n\nPress 'v' to view image or 'r' to process another", filter_name, self.output_file),
+        Err(error) => format!("x_x Failed to apply {} filter:\n\n{}\n\nPress 'r' to start over", filter_name, error),
+    };
+    self.processing_progress = 1.0;
+    self.state = AppState::Result;
+    self.progress_receiver = None;
+    self.result_receiver = None;
+    true
 }
 
 
@@ -726,6 +2076,7 @@ impl App {
     self.processing_progress = 0.0;
     self.selected_category = None;
     self.progress_receiver = None;
+    self.result_receiver = None;
   }
 
   pub fn cycle_category(&mut self) {
@@ -893,6 +2244,7 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
                   KeyCode::Char('q') => return Ok(()),
                   KeyCode::Char('h') => app.show_help = !app.show_help,
                   KeyCode::Char('c') => app.cycle_category(),
+                  KeyCode::Char('l') => app.linear_light = !app.linear_light,
                   KeyCode::Down | KeyCode::Char('j') => app.next_filter(),
                   KeyCode::Up | KeyCode::Char('k') => app.previous_filter(),
                   KeyCode::Enter => app.select_current_filter(),
@@ -902,11 +2254,15 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
               }
 
               AppState::ParameterInput => {
+                // Every printable character belongs to the value being typed, so quit and help
+                // live on Ctrl+Q and F1 here
                 match key.code {
-                  KeyCode::Char('q') => return Ok(()),
-                  KeyCode::Char('h') => app.show_help = !app.show_help,
+                  KeyCode::Char('q') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                  KeyCode::F(1) => app.show_help = !app.show_help,
                   KeyCode::Enter | KeyCode::Tab => app.next_parameter(),
                   KeyCode::Up => app.previous_parameter(),
+                  KeyCode::Left => app.cycle_choice(false),
+                  KeyCode::Right => app.cycle_choice(true),
                   KeyCode::Char(c) => {
                       app.current_input.push(c);
                   }
@@ -1312,10 +2668,11 @@ fn render_filter_selection(f: &mut Frame, app: &mut App, area: Rect) {
         .constraints([Constraint::Length(3), Constraint::Min(0)])
         .split(area);
 
+    let linear_text = if app.linear_light { "on" } else { "off" };
     let category_text = if let Some(cat) = &app.selected_category {
-        format!(" Category: {} (Press 'c' to cycle)", cat.name())
+        format!(" Category: {} (Press 'c' to cycle) | Linear light: {} (Press 'l' to toggle)", cat.name(), linear_text)
     } else {
-        " Category: All Filters (Press 'c' to cycle)".to_string()
+        format!(" Category: All Filters (Press 'c' to cycle) | Linear light: {} (Press 'l' to toggle)", linear_text)
     };
 
     let category_color = app.selected_category
@@ -1511,6 +2868,11 @@ fn render_parameter_input(f: &mut Frame, app: &App, area: Rect) {
                     ParamType::Integer { min, max } => format!("{} to {}", min, max),
                     ParamType::Float { min, max } => format!("{:.1} to {:.1}", min, max),
                     ParamType::Boolean => "true or false".to_string(),
+                    ParamType::Text => "free text".to_string(),
+                    ParamType::Choice { options } => options.join(" / "),
+                    ParamType::File => "path to an existing file".to_string(),
+                    ParamType::Color => "hex color such as #ff8800".to_string(),
+                    ParamType::Border => "clamp / mirror / wrap / constant[:#rrggbb]".to_string(),
                 };
                 vec![
                     Span::styled("Range: ", Style::default().fg(Color::Rgb(147, 112, 219))),
@@ -1576,6 +2938,10 @@ fn render_parameter_input(f: &mut Frame, app: &App, area: Rect) {
                 Span::raw("  • "),
                 Span::styled("↑ - Previous parameter", Style::default().fg(Color::Rgb(176, 196, 222))),
             ]),
+            Line::from(vec![
+                Span::raw("  • "),
+                Span::styled("←/→ - Cycle through choices", Style::default().fg(Color::Rgb(176, 196, 222))),
+            ]),
             Line::from(vec![
                 Span::raw("  • "),
                 Span::styled("Backspace - Delete characters", Style::default().fg(Color::Rgb(176, 196, 222))),
@@ -1586,7 +2952,7 @@ fn render_parameter_input(f: &mut Frame, app: &App, area: Rect) {
             ]),
             Line::from(vec![
                 Span::raw("  • "),
-                Span::styled("'q' - Quit application", Style::default().fg(Color::Rgb(176, 196, 222))),
+                Span::styled("Ctrl+Q - Quit application  •  F1 - Toggle help", Style::default().fg(Color::Rgb(176, 196, 222))),
             ]),
         ];
 
@@ -1924,6 +3290,9 @@ fn render_help_popup(f: &mut Frame, _app: &App, area: Rect) {
         Line::from(vec![
             Span::styled("  • 'c' - Cycle through filter categories", Style::default().fg(Color::Rgb(176, 196, 222))),
         ]),
+        Line::from(vec![
+            Span::styled("  • 'l' - Toggle linear light for every filter that supports it", Style::default().fg(Color::Rgb(176, 196, 222))),
+        ]),
         Line::from(vec![
             Span::styled("  • Enter - Select current filter", Style::default().fg(Color::Rgb(176, 196, 222))),
         ]),
@@ -1943,6 +3312,12 @@ fn render_help_popup(f: &mut Frame, _app: &App, area: Rect) {
         Line::from(vec![
             Span::styled("  • ↑ - Previous parameter", Style::default().fg(Color::Rgb(176, 196, 222))),
         ]),
+        Line::from(vec![
+            Span::styled("  • ←/→ - Cycle through the options of a choice", Style::default().fg(Color::Rgb(176, 196, 222))),
+        ]),
+        Line::from(vec![
+            Span::styled("  • Ctrl+Q quits and F1 toggles help, since letters are part of the value", Style::default().fg(Color::Rgb(176, 196, 222))),
+        ]),
         Line::from(vec![
             Span::styled("  • Default values are pre-filled", Style::default().fg(Color::Rgb(176, 196, 222))),
         ]),
@@ -1998,7 +3373,7 @@ fn render_help_popup(f: &mut Frame, _app: &App, area: Rect) {
         ]),
         Line::from(""),
         Line::from(vec![
-            Span::styled("Press 'h' again to close this help", Style::default().fg(Color::Rgb(105, 105, 105)).add_modifier(Modifier::ITALIC)),
+            Span::styled("Press 'h' again to close this help (F1 while entering parameters)", Style::default().fg(Color::Rgb(105, 105, 105)).add_modifier(Modifier::ITALIC)),
         ]),
     ];
 
@@ -0,0 +1,755 @@
+use std::{collections::HashMap, fs::{self, File}, io::BufWriter, path::Path, str::FromStr, sync::OnceLock};
+use image::{DynamicImage, ImageBuffer, Rgb};
+use rayon::{iter::{IndexedParallelIterator, ParallelIterator}, slice::{ParallelSlice, ParallelSliceMut}};
+use crate::{color::parse_hex_color, enhancement::LUMA_WEIGHTS, ProgressSender, Result, send_progress};
+
+// Colors are gathered into a 5 bits per channel histogram before building a palette.
+const HISTOGRAM_BITS: u32 = 5;
+const KMEANS_ITERATIONS: usize = 10;
+pub const MAX_PALETTE_SIZE: usize = 256;
+const BLUE_NOISE_SIZE: usize = 64;
+
+static BLUE_NOISE: OnceLock<Vec<f32>> = OnceLock::new();
+
+// Error diffusion kernels as (dx, dy, weight) with their divisor
+const FLOYD_STEINBERG: (&[(i32, i32, f32)], f32) = (&[(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)], 16.0);
+const ATKINSON: (&[(i32, i32, f32)], f32) = (&[(1, 0, 1.0), (2, 0, 1.0), (-1, 1, 1.0), (0, 1, 1.0), (1, 1, 1.0), (0, 2, 1.0)], 8.0);
+const JARVIS_JUDICE_NINKE: (&[(i32, i32, f32)], f32) = (&[
+    (1, 0, 7.0), (2, 0, 5.0),
+    (-2, 1, 3.0), (-1, 1, 5.0), (0, 1, 7.0), (1, 1, 5.0), (2, 1, 3.0),
+    (-2, 2, 1.0), (-1, 2, 3.0), (0, 2, 5.0), (1, 2, 3.0), (2, 2, 1.0),
+], 48.0);
+const SIERRA: (&[(i32, i32, f32)], f32) = (&[
+    (1, 0, 5.0), (2, 0, 3.0),
+    (-2, 1, 2.0), (-1, 1, 4.0), (0, 1, 5.0), (1, 1, 4.0), (2, 1, 2.0),
+    (-1, 2, 2.0), (0, 2, 3.0), (1, 2, 2.0),
+], 32.0);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuantizeMethod {
+    MedianCut,
+    Octree,
+    KMeans,
+}
+
+impl FromStr for QuantizeMethod {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "median-cut" => Ok(QuantizeMethod::MedianCut),
+            "octree" => Ok(QuantizeMethod::Octree),
+            "k-means" => Ok(QuantizeMethod::KMeans),
+            other => Err(format!("Unknown quantize method: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DitherMethod {
+    None,
+    FloydSteinberg,
+    Atkinson,
+    JarvisJudiceNinke,
+    Sierra,
+    Bayer(usize),
+    BlueNoise,
+}
+
+impl FromStr for DitherMethod {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "none" => Ok(DitherMethod::None),
+            "floyd-steinberg" => Ok(DitherMethod::FloydSteinberg),
+            "atkinson" => Ok(DitherMethod::Atkinson),
+            "jjn" | "jarvis-judice-ninke" => Ok(DitherMethod::JarvisJudiceNinke),
+            "sierra" => Ok(DitherMethod::Sierra),
+            "bayer2" => Ok(DitherMethod::Bayer(2)),
+            "bayer4" => Ok(DitherMethod::Bayer(4)),
+            "bayer8" => Ok(DitherMethod::Bayer(8)),
+            "blue-noise" => Ok(DitherMethod::BlueNoise),
+            other => Err(format!("Unknown dither method: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub colors: Vec<Rgb<u8>>,
+}
+
+// Average color and pixel count of one histogram bin
+#[derive(Debug, Clone, Copy)]
+struct ColorBin {
+    color: [f32; 3],
+    count: u32,
+}
+
+impl Palette {
+    pub fn load(path: impl AsRef<Path>) -> Result<Palette> {
+        let source = fs::read_to_string(path)?;
+        Palette::parse(&source)
+    }
+
+    // Accepts GIMP .gpl palettes or plain lists of hex colors, one per line.
+    pub fn parse(source: &str) -> Result<Palette> {
+        let mut colors = Vec::new();
+        // GIMP palettes use '#' for comments, so "#fff" there is a comment rather than a color
+        let gimp = source.lines().map(str::trim).find(|line| !line.is_empty()) == Some("GIMP Palette");
+
+        for (line_number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            let comment = if gimp { line.starts_with('#') } else { line.starts_with('#') && parse_hex_color(line).is_err() };
+            if line.is_empty()
+                || comment
+                || line.starts_with("//")
+                || line == "GIMP Palette"
+                || line.starts_with("Name:")
+                || line.starts_with("Columns:")
+            {
+                continue;
+            }
+
+            let invalid = || format!("Line {}: invalid color '{}'", line_number + 1, line);
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let color = match fields.as_slice() {
+                [r, g, b, ..] if gimp || r.parse::<u8>().is_ok() => Rgb([
+                    r.parse().map_err(|_| invalid())?,
+                    g.parse().map_err(|_| invalid())?,
+                    b.parse().map_err(|_| invalid())?,
+                ]),
+                _ if gimp => return Err(invalid().into()),
+                _ => parse_hex_color(fields[0]).map_err(|error| format!("Line {}: {}", line_number + 1, error))?,
+            };
+            colors.push(color);
+        }
+
+        if colors.is_empty() {
+            return Err("Palette contains no colors".into());
+        }
+        if colors.len() > MAX_PALETTE_SIZE {
+            return Err(format!("Palette has {} colors, at most {} are supported", colors.len(), MAX_PALETTE_SIZE).into());
+        }
+
+        Ok(Palette { colors })
+    }
+
+    pub fn from_image(img: &DynamicImage, size: usize, method: QuantizeMethod) -> Palette {
+        let rgb_img = img.to_rgb8();
+        let bins = color_histogram(&rgb_img);
+        let size = size.clamp(1, MAX_PALETTE_SIZE);
+
+        let colors = if bins.len() <= size {
+            bins.iter().map(|bin| bin.color).collect()
+        } else {
+            match method {
+                QuantizeMethod::MedianCut => median_cut(&bins, size),
+                QuantizeMethod::Octree => octree(&bins, size),
+                QuantizeMethod::KMeans => kmeans(&bins, median_cut(&bins, size)),
+            }
+        };
+
+        Palette {
+            colors: colors
+                .into_iter()
+                .map(|color| Rgb(color.map(|channel| channel.round().clamp(0.0, 255.0) as u8)))
+                .collect(),
+        }
+    }
+
+    // Evenly spaced grays from black to white; two levels give 1-bit output.
+    pub fn grayscale(levels: usize) -> Palette {
+        let levels = levels.clamp(2, MAX_PALETTE_SIZE);
+        Palette {
+            colors: (0..levels)
+                .map(|level| {
+                    let value = (level as f32 * 255.0 / (levels - 1) as f32).round() as u8;
+                    Rgb([value, value, value])
+                })
+                .collect(),
+        }
+    }
+
+    // Average per-channel distance from each color to its closest neighbour, used to scale ordered dither noise
+    fn spacing(&self) -> f32 {
+        if self.colors.len() < 2 {
+            return 255.0;
+        }
+
+        let colors: Vec<[f32; 3]> = self.colors.iter().map(|color| color.0.map(|channel| channel as f32)).collect();
+        let total: f32 = colors
+            .iter()
+            .enumerate()
+            .map(|(index, a)| {
+                colors
+                    .iter()
+                    .enumerate()
+                    .filter(|(other, _)| *other != index)
+                    .map(|(_, b)| (0..3).map(|channel| (a[channel] - b[channel]).powi(2)).sum::<f32>().sqrt())
+                    .fold(f32::MAX, f32::min)
+            })
+            .sum();
+        total / colors.len() as f32 / 3.0f32.sqrt()
+    }
+
+    pub fn nearest(&self, pixel: [f32; 3]) -> usize {
+        let mut best = 0;
+        let mut best_distance = f32::MAX;
+        for (index, color) in self.colors.iter().enumerate() {
+            let distance = weighted_distance(pixel, color.0.map(|channel| channel as f32));
+            if distance < best_distance {
+                best_distance = distance;
+                best = index;
+            }
+        }
+        best
+    }
+
+    // Writes the image as an 8-bit paletted PNG; pixels are matched to their nearest palette entry.
+    pub fn save_indexed_png(&self, img: &ImageBuffer<Rgb<u8>, Vec<u8>>, path: impl AsRef<Path>) -> Result<()> {
+        let (width, height) = img.dimensions();
+        let lookup: HashMap<[u8; 3], u8> = self.colors
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, color)| (color.0, index as u8))
+            .collect();
+
+        let indices: Vec<u8> = img
+            .as_raw()
+            .par_chunks_exact(3)
+            .map(|pixel| {
+                let pixel = [pixel[0], pixel[1], pixel[2]];
+                lookup.get(&pixel).copied().unwrap_or_else(|| self.nearest(pixel.map(|channel| channel as f32)) as u8)
+            })
+            .collect();
+
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(self.colors.iter().flat_map(|color| color.0).collect::<Vec<u8>>());
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&indices)?;
+        Ok(())
+    }
+}
+
+
+
+pub fn quantize(img: &DynamicImage, colors: usize, method: QuantizeMethod, dither_method: DitherMethod, progress_tx: Option<ProgressSender>) -> (ImageBuffer<Rgb<u8>, Vec<u8>>, Palette) {
+    send_progress(&progress_tx, 0.0);
+
+    let palette = Palette::from_image(img, colors, method);
+
+    send_progress(&progress_tx, 0.5);
+
+    let out_buffer = dither(img, &palette, dither_method, None);
+
+    send_progress(&progress_tx, 1.0);
+    (out_buffer, palette)
+}
+
+
+
+pub fn remap(img: &DynamicImage, palette: &Palette, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+    let mut out_buffer = ImageBuffer::new(width, height);
+
+    send_progress(&progress_tx, 0.0);
+
+    let in_pixels = rgb_img.as_raw();
+    let out_pixels = out_buffer.as_mut();
+
+    in_pixels
+        .par_chunks_exact(3)
+        .zip(out_pixels.par_chunks_exact_mut(3))
+        .for_each(|(in_pixel, out_pixel)| {
+            let index = palette.nearest([in_pixel[0] as f32, in_pixel[1] as f32, in_pixel[2] as f32]);
+            out_pixel.copy_from_slice(&palette.colors[index].0);
+        });
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+pub fn dither(img: &DynamicImage, palette: &Palette, method: DitherMethod, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    match method {
+        DitherMethod::None => remap(img, palette, progress_tx),
+        DitherMethod::FloydSteinberg => error_diffusion(img, palette, FLOYD_STEINBERG, progress_tx),
+        DitherMethod::Atkinson => error_diffusion(img, palette, ATKINSON, progress_tx),
+        DitherMethod::JarvisJudiceNinke => error_diffusion(img, palette, JARVIS_JUDICE_NINKE, progress_tx),
+        DitherMethod::Sierra => error_diffusion(img, palette, SIERRA, progress_tx),
+        DitherMethod::Bayer(size) => {
+            let matrix = bayer_matrix(size);
+            ordered_dither(img, palette, &matrix, size, progress_tx)
+        }
+        DitherMethod::BlueNoise => {
+            let map = BLUE_NOISE.get_or_init(blue_noise_map);
+            ordered_dither(img, palette, map, BLUE_NOISE_SIZE, progress_tx)
+        }
+    }
+}
+
+
+
+// Serpentine error diffusion; rows depend on the ones above so this runs sequentially.
+fn error_diffusion(img: &DynamicImage, palette: &Palette, kernel: (&[(i32, i32, f32)], f32), progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+    let (width, height) = (width as usize, height as usize);
+    let mut out_buffer = ImageBuffer::new(width as u32, height as u32);
+
+    send_progress(&progress_tx, 0.0);
+
+    let (offsets, divisor) = kernel;
+    let mut values: Vec<f32> = rgb_img.as_raw().iter().map(|&value| value as f32).collect();
+    let out_pixels: &mut [u8] = out_buffer.as_mut();
+
+    for y in 0..height {
+        let reverse = y % 2 == 1;
+        for step in 0..width {
+            let x = if reverse { width - 1 - step } else { step };
+            let idx = (y * width + x) * 3;
+
+            let pixel = [
+                values[idx].clamp(0.0, 255.0),
+                values[idx + 1].clamp(0.0, 255.0),
+                values[idx + 2].clamp(0.0, 255.0),
+            ];
+            let chosen = palette.colors[palette.nearest(pixel)].0;
+            out_pixels[idx..idx + 3].copy_from_slice(&chosen);
+
+            for &(dx, dy, weight) in offsets {
+                let dx = if reverse { -dx } else { dx };
+                let nx = x as i32 + dx;
+                let ny = y + dy as usize;
+                if nx < 0 || nx >= width as i32 || ny >= height {
+                    continue;
+                }
+
+                let target = (ny * width + nx as usize) * 3;
+                for channel in 0..3 {
+                    values[target + channel] += (pixel[channel] - chosen[channel] as f32) * weight / divisor;
+                }
+            }
+        }
+
+        if y == height / 2 {
+            send_progress(&progress_tx, 0.5);
+        }
+    }
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+// Threshold maps hold values in -0.5..0.5, scaled by the palette spacing before matching.
+fn ordered_dither(img: &DynamicImage, palette: &Palette, map: &[f32], size: usize, progress_tx: Option<ProgressSender>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rgb_img = img.to_rgb8();
+    let (width, height) = rgb_img.dimensions();
+    let mut out_buffer = ImageBuffer::new(width, height);
+
+    send_progress(&progress_tx, 0.0);
+
+    let spread = palette.spacing();
+    let in_pixels = rgb_img.as_raw();
+
+    out_buffer
+        .as_mut()
+        .par_chunks_exact_mut((width * 3) as usize)
+        .enumerate()
+        .for_each(|(y, out_row)| {
+            for x in 0..width as usize {
+                let in_idx = (y * width as usize + x) * 3;
+                let offset = map[(y % size) * size + x % size] * spread;
+                let pixel = [
+                    (in_pixels[in_idx] as f32 + offset).clamp(0.0, 255.0),
+                    (in_pixels[in_idx + 1] as f32 + offset).clamp(0.0, 255.0),
+                    (in_pixels[in_idx + 2] as f32 + offset).clamp(0.0, 255.0),
+                ];
+                out_row[x * 3..x * 3 + 3].copy_from_slice(&palette.colors[palette.nearest(pixel)].0);
+            }
+        });
+
+    send_progress(&progress_tx, 1.0);
+    out_buffer
+}
+
+
+
+// Builds the recursive Bayer index matrix of the given power-of-two size as centered thresholds.
+fn bayer_matrix(size: usize) -> Vec<f32> {
+    let mut matrix = vec![0usize];
+    let mut current = 1;
+
+    while current < size {
+        let next = current * 2;
+        let mut expanded = vec![0usize; next * next];
+        for y in 0..current {
+            for x in 0..current {
+                let value = 4 * matrix[y * current + x];
+                expanded[y * next + x] = value;
+                expanded[y * next + x + current] = value + 2;
+                expanded[(y + current) * next + x] = value + 3;
+                expanded[(y + current) * next + x + current] = value + 1;
+            }
+        }
+        matrix = expanded;
+        current = next;
+    }
+
+    let cells = (size * size) as f32;
+    matrix.iter().map(|&rank| (rank as f32 + 0.5) / cells - 0.5).collect()
+}
+
+
+
+// Void-and-cluster threshold map (Ulichney). Points are ranked by repeatedly filling the
+// largest void of a tileable Gaussian energy field, which keeps the pattern free of low frequencies.
+fn blue_noise_map() -> Vec<f32> {
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    const SIGMA: f32 = 1.5;
+    let size = BLUE_NOISE_SIZE;
+    let cells = size * size;
+
+    let kernel: Vec<f32> = (0..cells)
+        .map(|index| {
+            let dx = (index % size).min(size - index % size) as f32;
+            let dy = (index / size).min(size - index / size) as f32;
+            (-(dx * dx + dy * dy) / (2.0 * SIGMA * SIGMA)).exp()
+        })
+        .collect();
+
+    let splat = |energy: &mut [f32], point: usize, sign: f32| {
+        let (px, py) = (point % size, point / size);
+        for y in 0..size {
+            for x in 0..size {
+                let offset = ((y + size - py) % size) * size + (x + size - px) % size;
+                energy[y * size + x] += sign * kernel[offset];
+            }
+        }
+    };
+    let extreme = |energy: &[f32], filled: &[bool], want: bool| -> usize {
+        (0..cells)
+            .filter(|&index| filled[index] == want)
+            .max_by(|&a, &b| {
+                let order = energy[a].total_cmp(&energy[b]);
+                if want { order } else { order.reverse() }
+            })
+            .unwrap_or(0)
+    };
+
+    // Random initial pattern, then relax it until the tightest cluster is also the largest void.
+    // Ties in the energy field can make two points trade places forever, so the swaps are capped.
+    let mut rng = SmallRng::seed_from_u64(0x5eed_b1e5);
+    let mut filled = vec![false; cells];
+    let mut energy = vec![0.0f32; cells];
+    let initial = cells / 10;
+    while filled.iter().filter(|&&set| set).count() < initial {
+        let point = rng.random_range(0..cells);
+        if !filled[point] {
+            filled[point] = true;
+            splat(&mut energy, point, 1.0);
+        }
+    }
+
+    for _ in 0..cells {
+        let cluster = extreme(&energy, &filled, true);
+        filled[cluster] = false;
+        splat(&mut energy, cluster, -1.0);
+
+        let void = extreme(&energy, &filled, false);
+        filled[void] = true;
+        splat(&mut energy, void, 1.0);
+
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0usize; cells];
+
+    // Remove clusters from a copy of the initial pattern to rank its points downwards
+    let mut removing = filled.clone();
+    let mut removing_energy = energy.clone();
+    for rank in (0..initial).rev() {
+        let cluster = extreme(&removing_energy, &removing, true);
+        removing[cluster] = false;
+        splat(&mut removing_energy, cluster, -1.0);
+        ranks[cluster] = rank;
+    }
+
+    // Fill voids to rank the remaining points upwards
+    for rank in initial..cells {
+        let void = extreme(&energy, &filled, false);
+        filled[void] = true;
+        splat(&mut energy, void, 1.0);
+        ranks[void] = rank;
+    }
+
+    ranks.iter().map(|&rank| (rank as f32 + 0.5) / cells as f32 - 0.5).collect()
+}
+
+
+
+// Squared distance with channels weighted by their luma contribution
+fn weighted_distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (0..3).map(|channel| LUMA_WEIGHTS[channel] * (a[channel] - b[channel]).powi(2)).sum()
+}
+
+
+
+fn color_histogram(rgb_img: &ImageBuffer<Rgb<u8>, Vec<u8>>) -> Vec<ColorBin> {
+    let shift = 8 - HISTOGRAM_BITS;
+    let bin_count = 1usize << (3 * HISTOGRAM_BITS);
+
+    let (sums, counts) = rgb_img
+        .as_raw()
+        .par_chunks(3 * 4096)
+        .fold(|| (vec![[0u64; 3]; bin_count], vec![0u32; bin_count]), |(mut sums, mut counts), chunk| {
+            for pixel in chunk.chunks_exact(3) {
+                let index = ((pixel[0] as usize >> shift) << (2 * HISTOGRAM_BITS))
+                    | ((pixel[1] as usize >> shift) << HISTOGRAM_BITS)
+                    | (pixel[2] as usize >> shift);
+                for channel in 0..3 {
+                    sums[index][channel] += pixel[channel] as u64;
+                }
+                counts[index] += 1;
+            }
+            (sums, counts)
+        })
+        .reduce(|| (vec![[0u64; 3]; bin_count], vec![0u32; bin_count]), |(mut sums, mut counts), (other_sums, other_counts)| {
+            for index in 0..bin_count {
+                for channel in 0..3 {
+                    sums[index][channel] += other_sums[index][channel];
+                }
+                counts[index] += other_counts[index];
+            }
+            (sums, counts)
+        });
+
+    sums.iter()
+        .zip(counts.iter())
+        .filter(|(_, count)| **count > 0)
+        .map(|(sum, &count)| ColorBin {
+            color: sum.map(|channel| channel as f32 / count as f32),
+            count,
+        })
+        .collect()
+}
+
+
+
+fn mean_color(bins: &[ColorBin]) -> [f32; 3] {
+    let mut sum = [0.0f64; 3];
+    let mut total = 0.0f64;
+    for bin in bins {
+        for (total_channel, channel) in sum.iter_mut().zip(bin.color) {
+            *total_channel += channel as f64 * bin.count as f64;
+        }
+        total += bin.count as f64;
+    }
+    sum.map(|channel| (channel / total.max(1.0)) as f32)
+}
+
+
+
+// Repeatedly splits the box with the widest weighted channel range at its pixel median.
+fn median_cut(bins: &[ColorBin], size: usize) -> Vec<[f32; 3]> {
+    let mut boxes: Vec<Vec<ColorBin>> = vec![bins.to_vec()];
+
+    while boxes.len() < size {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(index, colors)| {
+                let (channel, range) = widest_channel(colors);
+                let population: u64 = colors.iter().map(|bin| bin.count as u64).sum();
+                (index, channel, range * (population as f32).sqrt())
+            })
+            .max_by(|a, b| a.2.total_cmp(&b.2));
+
+        let Some((index, channel, _)) = widest else {
+            break;
+        };
+
+        let mut colors = boxes.swap_remove(index);
+        colors.sort_by(|a, b| a.color[channel].total_cmp(&b.color[channel]));
+
+        let half: u64 = colors.iter().map(|bin| bin.count as u64).sum::<u64>() / 2;
+        let mut running = 0u64;
+        let mut split = 1;
+        for (position, bin) in colors.iter().enumerate() {
+            running += bin.count as u64;
+            if running >= half {
+                split = (position + 1).clamp(1, colors.len() - 1);
+                break;
+            }
+        }
+
+        let upper = colors.split_off(split);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|colors| mean_color(colors)).collect()
+}
+
+
+
+fn widest_channel(colors: &[ColorBin]) -> (usize, f32) {
+    (0..3)
+        .map(|channel| {
+            let (min, max) = colors.iter().fold((f32::MAX, f32::MIN), |(min, max), bin| {
+                (min.min(bin.color[channel]), max.max(bin.color[channel]))
+            });
+            (channel, (max - min) * LUMA_WEIGHTS[channel].sqrt())
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((0, 0.0))
+}
+
+
+
+struct OctreeNode {
+    children: [Option<usize>; 8],
+    sum: [f64; 3],
+    count: u64,
+    level: usize,
+    is_leaf: bool,
+}
+
+// Inserts every color into an 8-level octree, then folds the deepest nodes into their parents
+// until no more than `size` leaves are left.
+fn octree(bins: &[ColorBin], size: usize) -> Vec<[f32; 3]> {
+    const DEPTH: usize = 8;
+
+    let mut nodes = vec![OctreeNode { children: [None; 8], sum: [0.0; 3], count: 0, level: 0, is_leaf: false }];
+
+    for bin in bins {
+        let rgb = bin.color.map(|channel| channel.round().clamp(0.0, 255.0) as u8);
+        let mut current = 0;
+        for level in 0..DEPTH {
+            let bit = 7 - level;
+            let child = (((rgb[0] >> bit) & 1) << 2 | ((rgb[1] >> bit) & 1) << 1 | ((rgb[2] >> bit) & 1)) as usize;
+            current = match nodes[current].children[child] {
+                Some(next) => next,
+                None => {
+                    nodes.push(OctreeNode {
+                        children: [None; 8],
+                        sum: [0.0; 3],
+                        count: 0,
+                        level: level + 1,
+                        is_leaf: level + 1 == DEPTH,
+                    });
+                    let next = nodes.len() - 1;
+                    nodes[current].children[child] = Some(next);
+                    next
+                }
+            };
+        }
+
+        for channel in 0..3 {
+            nodes[current].sum[channel] += bin.color[channel] as f64 * bin.count as f64;
+        }
+        nodes[current].count += bin.count as u64;
+    }
+
+    let mut leaves = nodes.iter().filter(|node| node.is_leaf).count();
+
+    for level in (0..DEPTH).rev() {
+        if leaves <= size {
+            break;
+        }
+
+        // Fold the least populated parents first so busy regions keep their detail
+        let mut parents: Vec<usize> = (0..nodes.len())
+            .filter(|&index| nodes[index].level == level && !nodes[index].is_leaf)
+            .collect();
+        parents.sort_by_key(|&index| subtree_count(&nodes, index));
+
+        for parent in parents {
+            if leaves <= size {
+                break;
+            }
+
+            let children: Vec<usize> = nodes[parent].children.iter().flatten().copied().collect();
+            for &child in &children {
+                for channel in 0..3 {
+                    nodes[parent].sum[channel] += nodes[child].sum[channel];
+                }
+                nodes[parent].count += nodes[child].count;
+                nodes[child].is_leaf = false;
+                nodes[child].count = 0;
+            }
+            nodes[parent].children = [None; 8];
+            nodes[parent].is_leaf = true;
+            leaves = leaves + 1 - children.len();
+        }
+    }
+
+    nodes
+        .iter()
+        .filter(|node| node.is_leaf && node.count > 0)
+        .map(|node| node.sum.map(|channel| (channel / node.count as f64) as f32))
+        .collect()
+}
+
+
+
+fn subtree_count(nodes: &[OctreeNode], index: usize) -> u64 {
+    nodes[index].count + nodes[index].children.iter().flatten().map(|&child| subtree_count(nodes, child)).sum::<u64>()
+}
+
+
+
+// Lloyd iterations over the histogram bins, seeded with the median-cut palette.
+fn kmeans(bins: &[ColorBin], mut centers: Vec<[f32; 3]>) -> Vec<[f32; 3]> {
+    for _ in 0..KMEANS_ITERATIONS {
+        let mut sums = vec![[0.0f64; 3]; centers.len()];
+        let mut counts = vec![0u64; centers.len()];
+
+        for bin in bins {
+            let nearest = centers
+                .iter()
+                .enumerate()
+                .map(|(index, center)| (index, weighted_distance(bin.color, *center)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(index, _)| index)
+                .unwrap_or(0);
+
+            for (total_channel, channel) in sums[nearest].iter_mut().zip(bin.color) {
+                *total_channel += channel as f64 * bin.count as f64;
+            }
+            counts[nearest] += bin.count as u64;
+        }
+
+        let mut moved = false;
+        for (index, center) in centers.iter_mut().enumerate() {
+            if counts[index] == 0 {
+                continue;
+            }
+            let updated = sums[index].map(|channel| (channel / counts[index] as f64) as f32);
+            if weighted_distance(updated, *center) > 0.01 {
+                moved = true;
+            }
+            *center = updated;
+        }
+
+        if !moved {
+            break;
+        }
+    }
+
+    centers
+}